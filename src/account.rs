@@ -0,0 +1,285 @@
+use crate::budget::Budget;
+use crate::color::{paint, Role};
+use crate::config::Config;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::table::{Column, Table};
+use crate::utils;
+use clap::ValueEnum;
+use rusqlite::Connection;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum AccountKind {
+    Bank,
+    Cash,
+    Credit,
+}
+
+impl AccountKind {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Bank => "bank",
+            Self::Cash => "cash",
+            Self::Credit => "credit",
+        }
+    }
+}
+
+pub struct Account {
+    pub account_id: Option<u32>,
+    pub name: String,
+    pub kind: AccountKind,
+    pub balance: f64,
+    pub currency: Option<String>,
+}
+
+impl Account {
+    pub fn new(name: &str, kind: &AccountKind, balance: &f64) -> Account {
+        Account {
+            account_id: None,
+            name: utils::capitalize(name),
+            kind: *kind,
+            balance: *balance,
+            currency: None,
+        }
+    }
+}
+
+pub struct Allocation {
+    pub currency: String,
+    pub accounts: f64,
+    pub budgets: f64,
+    pub unassigned: f64,
+}
+
+impl Allocation {
+    pub fn unallocated(&self) -> f64 {
+        self.accounts - self.budgets - self.unassigned
+    }
+}
+
+pub fn account_effect(action: &str, amount: &f64) -> f64 {
+    match action {
+        "Increase funds" => *amount,
+        "Reduce funds" => -amount,
+        _ => 0.0,
+    }
+}
+
+pub fn create_account_tables(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS accounts (
+            account_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            kind TEXT NOT NULL,
+            balance REAL NOT NULL,
+            currency TEXT
+        );
+        ALTER TABLE records ADD COLUMN account_id INTEGER REFERENCES accounts(account_id);
+        ALTER TABLE income ADD COLUMN account_id INTEGER REFERENCES accounts(account_id);";
+    db.execute_batch(query)
+}
+
+pub fn insert_account(db: &Connection, account: &Account) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO accounts (name, kind, balance, currency)
+        VALUES (?1, ?2, ?3, ?4);";
+    db.execute(
+        query,
+        (
+            &account.name,
+            account.kind.value(),
+            &account.balance,
+            &account.currency,
+        ),
+    )
+}
+
+fn map_account(row: &rusqlite::Row) -> Result<Account, rusqlite::Error> {
+    let kind: String = row.get(2)?;
+    Ok(Account {
+        account_id: row.get(0)?,
+        name: row.get(1)?,
+        kind: AccountKind::from_str(&kind, true).unwrap_or(AccountKind::Bank),
+        balance: row.get(3)?,
+        currency: row.get(4)?,
+    })
+}
+
+pub fn select_account_by_id(db: &Connection, id: &u32) -> Result<Account, rusqlite::Error> {
+    let query = "
+        SELECT account_id, name, kind, balance, currency
+        FROM accounts
+        WHERE account_id = ?1;";
+    db.query_row(query, [id], map_account)
+}
+
+pub fn select_all_accounts(db: &Connection) -> Result<Vec<Account>, rusqlite::Error> {
+    let query = "
+        SELECT account_id, name, kind, balance, currency
+        FROM accounts
+        ORDER BY account_id;";
+    let mut stmt = db.prepare(query)?;
+    let accounts = stmt
+        .query_map([], map_account)?
+        .collect::<Result<Vec<Account>, rusqlite::Error>>()?;
+    Ok(accounts)
+}
+
+pub fn update_account_balance(
+    db: &Connection,
+    id: &u32,
+    change: &f64,
+) -> Result<usize, rusqlite::Error> {
+    let query = "
+        UPDATE accounts
+        SET balance = balance + ?1
+        WHERE account_id = ?2;";
+    db.execute(query, (change, id))
+}
+
+pub fn delete_account_by_id(db: &Connection, id: &u32) -> Result<usize, rusqlite::Error> {
+    db.execute("DELETE FROM accounts WHERE account_id = ?1;", [id])
+}
+
+pub fn count_account_entries(db: &Connection, id: &u32) -> Result<u32, rusqlite::Error> {
+    let query = "
+        SELECT (SELECT COUNT(*) FROM records WHERE account_id = ?1)
+            + (SELECT COUNT(*) FROM income WHERE account_id = ?1);";
+    db.query_row(query, [id], |row| row.get(0))
+}
+
+pub fn build_allocations(
+    accounts: &[Account],
+    budgets: &[Budget],
+    unassigned: f64,
+    base: &str,
+) -> Vec<Allocation> {
+    let code_of = |currency: &Option<String>| currency.clone().unwrap_or(base.to_string());
+    let mut codes: Vec<String> = accounts
+        .iter()
+        .map(|account| code_of(&account.currency))
+        .chain(budgets.iter().map(|budget| code_of(&budget.currency)))
+        .collect();
+    codes.sort();
+    codes.dedup();
+    codes
+        .into_iter()
+        .map(|code| Allocation {
+            accounts: accounts
+                .iter()
+                .filter(|account| code_of(&account.currency) == code)
+                .fold(0.0, |total, account| total + account.balance),
+            budgets: budgets
+                .iter()
+                .filter(|budget| code_of(&budget.currency) == code)
+                .fold(0.0, |total, budget| total + budget.current_funds),
+            unassigned: if code == base { unassigned } else { 0.0 },
+            currency: code,
+        })
+        .collect()
+}
+
+pub fn print_accounts(accounts: &[Account], allocations: &[Allocation], config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = accounts
+            .iter()
+            .map(|account| {
+                vec![
+                    Cell::optional_number(account.account_id.map(f64::from)),
+                    Cell::text(&account.name),
+                    Cell::text(account.kind.value()),
+                    Cell::Text(config.currency_code(&account.currency)),
+                    Cell::Number(account.balance),
+                ]
+            })
+            .collect();
+        let headers = ["id", "account", "kind", "currency", "balance"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("ACCOUNT"),
+        Column::left("KIND").keep(),
+        Column::left("CUR").keep(),
+        Column::right("BALANCE"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for account in accounts {
+        let money = config.money_format(&account.currency);
+        table.add_row(vec![
+            account.account_id.unwrap().to_string(),
+            account.name.to_owned(),
+            account.kind.value().to_string(),
+            config.currency_code(&account.currency),
+            money.format(account.balance),
+        ]);
+        table.style_cell(4, config.amount_style(account.balance));
+    }
+    if accounts.is_empty() {
+        return table.print();
+    }
+    table.add_separator();
+    let held = |allocation: &&Allocation| {
+        accounts
+            .iter()
+            .any(|account| config.currency_code(&account.currency) == allocation.currency)
+    };
+    for allocation in allocations.iter().filter(held) {
+        let money = config.money_format(&Some(allocation.currency.to_owned()));
+        table.add_row(vec![
+            String::new(),
+            String::from("NET WORTH"),
+            String::new(),
+            allocation.currency.to_owned(),
+            money.format(allocation.accounts),
+        ]);
+        table.style_cell(4, config.amount_style(allocation.accounts));
+    }
+    table.print();
+    let unbalanced: Vec<&Allocation> = allocations
+        .iter()
+        .filter(|allocation| allocation.unallocated().abs() >= 0.005)
+        .collect();
+    if !unbalanced.is_empty() {
+        println!();
+    }
+    for allocation in unbalanced {
+        let money = config.money_format(&Some(allocation.currency.to_owned()));
+        let unallocated = allocation.unallocated();
+        let message = if unallocated > 0.0 {
+            format!(
+                "{} in accounts is not allocated to any budget",
+                money.format(unallocated)
+            )
+        } else {
+            format!(
+                "Budgets hold {} more than the accounts",
+                money.format(-unallocated)
+            )
+        };
+        println!("{}", paint(&message, config.style(Role::Warning)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_allocations_ok() {
+        let config = Config::default();
+        let mut card = Account::new("Card", &AccountKind::Credit, &-200.0);
+        card.currency = Some(String::from("EUR"));
+        let accounts = [Account::new("Bank", &AccountKind::Bank, &1500.0), card];
+        let budgets = [Budget::new("Food", &400.0), Budget::new("Rent", &800.0)];
+        let allocations = build_allocations(&accounts, &budgets, 100.0, &config.currency.code);
+        assert_eq!(allocations.len(), 2);
+        let base = allocations
+            .iter()
+            .find(|allocation| allocation.currency == config.currency.code)
+            .unwrap();
+        assert_eq!(base.unallocated(), 200.0);
+        assert_eq!(account_effect("Reduce funds", &5.0), -5.0);
+        assert_eq!(account_effect("Transfer in", &5.0), 0.0);
+    }
+}
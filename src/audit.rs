@@ -0,0 +1,239 @@
+use crate::color::Role;
+use crate::config::Config;
+use crate::dates::format_datetime;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::table::{Column, Table};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+pub struct AuditEntry {
+    pub audit_id: Option<u32>,
+    pub budget_id: Option<u32>,
+    pub action: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        budget_id: &u32,
+        action: &str,
+        field: &str,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        actor: &Option<String>,
+    ) -> AuditEntry {
+        AuditEntry {
+            budget_id: Some(*budget_id),
+            ..AuditEntry::unscoped(action, field, old_value, new_value, actor)
+        }
+    }
+
+    pub fn unscoped(
+        action: &str,
+        field: &str,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        actor: &Option<String>,
+    ) -> AuditEntry {
+        AuditEntry {
+            audit_id: None,
+            budget_id: None,
+            action: action.to_string(),
+            field: field.to_string(),
+            old_value,
+            new_value,
+            actor: actor.to_owned(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+pub fn print_audit_entries(entries: &Vec<AuditEntry>, config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    Cell::Text(entry.created_at.to_rfc3339()),
+                    Cell::optional_number(entry.budget_id.map(f64::from)),
+                    Cell::text(&entry.action),
+                    Cell::text(&entry.field),
+                    Cell::optional_text(&entry.old_value),
+                    Cell::optional_text(&entry.new_value),
+                    Cell::optional_text(&entry.actor),
+                ]
+            })
+            .collect();
+        let headers = [
+            "date",
+            "budget_id",
+            "action",
+            "field",
+            "old_value",
+            "new_value",
+            "actor",
+        ];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let tz = config.tz();
+    let datetime_format = config.datetime_format();
+    let mut table = Table::new(vec![
+        Column::left("DATE").keep(),
+        Column::right("ID"),
+        Column::left("ACTION"),
+        Column::left("FIELD"),
+        Column::left("OLD VALUE"),
+        Column::left("NEW VALUE"),
+        Column::left("ACTOR"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for entry in entries {
+        table.add_row(vec![
+            format_datetime(&entry.created_at, &tz, &datetime_format),
+            entry.budget_id.map(|id| id.to_string()).unwrap_or_default(),
+            entry.action.to_owned(),
+            entry.field.to_owned(),
+            entry.old_value.clone().unwrap_or_default(),
+            entry.new_value.clone().unwrap_or_default(),
+            entry.actor.clone().unwrap_or_default(),
+        ]);
+    }
+    table.print();
+}
+
+pub fn create_audit_table(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS audit (
+            audit_id INTEGER PRIMARY KEY,
+            budget_id INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            actor TEXT,
+            created_at TEXT
+        );";
+    db.execute(query, ())?;
+    Ok(())
+}
+
+pub fn allow_unscoped_audit(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE audit_unscoped (
+            audit_id INTEGER PRIMARY KEY,
+            budget_id INTEGER,
+            action TEXT NOT NULL,
+            field TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            actor TEXT,
+            created_at TEXT
+        );
+        INSERT INTO audit_unscoped
+        SELECT audit_id, budget_id, action, field, old_value, new_value, actor, created_at
+        FROM audit;
+        DROP TABLE audit;
+        ALTER TABLE audit_unscoped RENAME TO audit;
+        CREATE INDEX IF NOT EXISTS audit_budget_date
+        ON audit (budget_id, created_at, audit_id);";
+    db.execute_batch(query)
+}
+
+pub fn insert_audit_entry(db: &Connection, entry: &AuditEntry) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO audit (budget_id, action, field, old_value, new_value, actor, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);";
+    db.execute(
+        query,
+        (
+            &entry.budget_id,
+            &entry.action,
+            &entry.field,
+            &entry.old_value,
+            &entry.new_value,
+            &entry.actor,
+            &entry.created_at,
+        ),
+    )
+}
+
+pub fn get_audit_entries_by_budget(
+    db: &Connection,
+    budget_id: &u32,
+) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+    let query = "
+        SELECT audit_id, budget_id, action, field, old_value, new_value, actor, created_at
+        FROM audit
+        WHERE budget_id = ?1
+        ORDER BY audit_id DESC;";
+    let mut stmt = db.prepare(query)?;
+    let entry_iter = stmt.query_map([budget_id], map_audit_entry)?;
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+pub fn get_all_audit_entries(db: &Connection) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+    let query = "
+        SELECT audit_id, budget_id, action, field, old_value, new_value, actor, created_at
+        FROM audit
+        ORDER BY audit_id DESC;";
+    let mut stmt = db.prepare(query)?;
+    let entry_iter = stmt.query_map([], map_audit_entry)?;
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+fn map_audit_entry(row: &rusqlite::Row) -> Result<AuditEntry, rusqlite::Error> {
+    Ok(AuditEntry {
+        audit_id: row.get(0)?,
+        budget_id: row.get(1)?,
+        action: row.get(2)?,
+        field: row.get(3)?,
+        old_value: row.get(4)?,
+        new_value: row.get(5)?,
+        actor: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::migrate;
+
+    fn entry(budget_id: u32, new_value: &str) -> AuditEntry {
+        AuditEntry::new(
+            &budget_id,
+            "Rename budget",
+            "name",
+            Some(String::from("Food")),
+            Some(new_value.to_string()),
+            &Some(String::from("alice")),
+        )
+    }
+
+    #[test]
+    fn insert_audit_entry_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_audit_entry(&db, &entry(1, "Groceries")).unwrap();
+        insert_audit_entry(&db, &entry(2, "Rent")).unwrap();
+        insert_audit_entry(&db, &entry(1, "Meals")).unwrap();
+        let entries = get_audit_entries_by_budget(&db, &1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].new_value, Some(String::from("Meals")));
+        assert_eq!(entries[0].old_value, Some(String::from("Food")));
+        assert_eq!(entries[0].actor, Some(String::from("alice")));
+        assert_eq!(get_all_audit_entries(&db).unwrap().len(), 3);
+    }
+}
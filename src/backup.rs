@@ -0,0 +1,211 @@
+use crate::database::{get_schema_version, migrate, SCHEMA_VERSION};
+use crate::error::Error;
+use chrono::Local;
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT_DIR: &str = "snapshots";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+const TIMESTAMP_LEN: usize = 17;
+
+pub fn backup_db(db: &Connection, path: &Path) -> Result<(), Error> {
+    if path.exists() {
+        return Err(Error::Invalid(format!("{} already exists", path.display())));
+    }
+    db.backup(DatabaseName::Main, path, None)?;
+    Ok(())
+}
+
+pub fn restore_db(db: &mut Connection, path: &Path) -> Result<(), Error> {
+    validate_backup(path)?;
+    db.restore(DatabaseName::Main, path, None::<fn(Progress)>)?;
+    migrate(db)?;
+    Ok(())
+}
+
+pub fn validate_backup(path: &Path) -> Result<i32, Error> {
+    if !path.is_file() {
+        return Err(Error::Invalid(format!("{} not found", path.display())));
+    }
+    let src = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let integrity: String = src.query_row("PRAGMA quick_check;", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(Error::Invalid(format!(
+            "{} is corrupted: {}",
+            path.display(),
+            integrity
+        )));
+    }
+    let version = get_schema_version(&src)?;
+    if version > SCHEMA_VERSION {
+        return Err(Error::Invalid(format!(
+            "{} has schema version {}, but this version of budget supports up to {}",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        )));
+    }
+    let query = "
+        SELECT COUNT(*)
+        FROM sqlite_master
+        WHERE type = 'table' AND name IN ('budgets', 'records');";
+    let tables: i32 = src.query_row(query, [], |row| row.get(0))?;
+    if tables != 2 {
+        return Err(Error::Invalid(format!(
+            "{} is not a budget database",
+            path.display()
+        )));
+    }
+    Ok(version)
+}
+
+pub fn default_backup_path(db: &Connection) -> Result<PathBuf, Error> {
+    let db_path = get_db_path(db)?;
+    let stem = get_db_stem(&db_path);
+    let name = format!(
+        "{}-backup-{}.db3",
+        stem,
+        Local::now().format(TIMESTAMP_FORMAT)
+    );
+    Ok(db_path.with_file_name(name))
+}
+
+pub fn snapshot_db(db: &Connection, keep: usize) -> Result<PathBuf, Error> {
+    let db_path = get_db_path(db)?;
+    let stem = get_db_stem(&db_path);
+    let dir = db_path.with_file_name(SNAPSHOT_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}-{}.db3",
+        stem,
+        Local::now().format(TIMESTAMP_FORMAT)
+    ));
+    db.backup(DatabaseName::Main, &path, None)?;
+    prune_snapshots(&dir, &stem, keep)?;
+    Ok(path)
+}
+
+fn prune_snapshots(dir: &Path, stem: &str, keep: usize) -> Result<(), Error> {
+    let prefix = format!("{}-", stem);
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let timestamp = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".db3"));
+        if let Some(timestamp) = timestamp {
+            if timestamp.len() == TIMESTAMP_LEN && timestamp.chars().all(|c| c.is_ascii_digit()) {
+                snapshots.push(path);
+            }
+        }
+    }
+    snapshots.sort();
+    let excess = snapshots.len().saturating_sub(keep);
+    for path in &snapshots[..excess] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn get_db_path(db: &Connection) -> Result<PathBuf, Error> {
+    match db.path() {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Err(Error::Invalid(String::from(
+            "the database is not stored in a file",
+        ))),
+    }
+}
+
+fn get_db_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("budget")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{insert_budget, select_all_budgets, Budget};
+    use std::process;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("budget-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open(path: &Path) -> Connection {
+        let db = Connection::open(path).unwrap();
+        migrate(&db).unwrap();
+        db
+    }
+
+    #[test]
+    fn backup_restore_ok() {
+        let dir = temp_dir("restore");
+        let mut db = open(&dir.join("budget.db3"));
+        insert_budget(&db, &Budget::new("Food", &300.0)).unwrap();
+        let backup = dir.join("backup.db3");
+        backup_db(&db, &backup).unwrap();
+        assert_eq!(validate_backup(&backup).unwrap(), SCHEMA_VERSION);
+        db.execute("DELETE FROM budgets;", ()).unwrap();
+        restore_db(&mut db, &backup).unwrap();
+        let budgets = select_all_budgets(&db).unwrap();
+        assert_eq!(budgets.len(), 1);
+        assert_eq!(budgets[0].name, "Food");
+        assert!(backup_db(&db, &backup).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn validate_backup_ko() {
+        let dir = temp_dir("invalid");
+        let text = dir.join("notes.db3");
+        fs::write(&text, "not a database").unwrap();
+        assert!(validate_backup(&text).is_err());
+        let empty = dir.join("empty.db3");
+        Connection::open(&empty)
+            .unwrap()
+            .execute("CREATE TABLE notes (text TEXT);", ())
+            .unwrap();
+        assert!(validate_backup(&empty).is_err());
+        assert!(validate_backup(&dir.join("missing.db3")).is_err());
+        let mut db = open(&dir.join("budget.db3"));
+        insert_budget(&db, &Budget::new("Food", &300.0)).unwrap();
+        assert!(restore_db(&mut db, &text).is_err());
+        assert_eq!(select_all_budgets(&db).unwrap().len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshot_db_ok() {
+        let dir = temp_dir("snapshot");
+        let db = open(&dir.join("budget.db3"));
+        let snapshots = dir.join(SNAPSHOT_DIR);
+        fs::create_dir_all(&snapshots).unwrap();
+        for timestamp in ["20240101000000000", "20240102000000000"] {
+            fs::write(snapshots.join(format!("budget-{}.db3", timestamp)), "").unwrap();
+        }
+        fs::write(snapshots.join("budget-manual.db3"), "").unwrap();
+        let path = snapshot_db(&db, 2).unwrap();
+        assert!(validate_backup(&path).is_ok());
+        let mut names: Vec<String> = fs::read_dir(&snapshots)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "budget-20240102000000000.db3");
+        assert_eq!(names[2], "budget-manual.db3");
+        assert!(snapshot_db(&Connection::open_in_memory().unwrap(), 2).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,60 @@
+use crate::chart::sparkline;
+use crate::color::{paint, Role};
+use crate::config::Config;
+use crate::forecast::{format_exhaustion, Forecast};
+use crate::goal::Goal;
+use crate::hierarchy::tree_name;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::table::{Column, Table};
 use crate::utils;
-use accounting::Accounting;
+use clap::ValueEnum;
 use rusqlite::Connection;
+use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct Budget {
     pub budget_id: Option<u32>,
     pub name: String,
     pub initial_funds: f64,
     pub current_funds: f64,
+    pub currency: Option<String>,
+    pub warning_threshold: Option<f64>,
+    pub goal: Option<Goal>,
+    pub parent_id: Option<u32>,
+    pub spending_cap: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Status {
+    Over,
+    Warning,
+    Healthy,
+}
+
+impl Status {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Over => "over",
+            Self::Warning => "warning",
+            Self::Healthy => "healthy",
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        match self {
+            Self::Over => Role::Negative,
+            Self::Warning => Role::Warning,
+            Self::Healthy => Role::Positive,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SortKey {
+    Name,
+    Current,
+    Used,
+    Percent,
 }
 
 impl Budget {
@@ -16,6 +64,33 @@ impl Budget {
             name: utils::capitalize(name),
             initial_funds: *funds,
             current_funds: *funds,
+            currency: None,
+            warning_threshold: None,
+            goal: None,
+            parent_id: None,
+            spending_cap: None,
+        }
+    }
+
+    pub fn spent(&self) -> f64 {
+        self.initial_funds - self.current_funds
+    }
+
+    pub fn percent_used(&self) -> Option<f64> {
+        if self.initial_funds > 0.0 {
+            Some(self.spent() / self.initial_funds * 100.0)
+        } else {
+            None
+        }
+    }
+
+    pub fn status(&self, default_threshold: &f64) -> Status {
+        let threshold = self.warning_threshold.unwrap_or(*default_threshold);
+        match self.percent_used() {
+            _ if self.current_funds < 0.0 => Status::Over,
+            Some(percent) if percent > 100.0 => Status::Over,
+            Some(percent) if percent >= threshold => Status::Warning,
+            _ => Status::Healthy,
         }
     }
 
@@ -44,23 +119,280 @@ impl Budget {
     }
 }
 
-pub fn print_budgets(budgets: &Vec<Budget>) {
-    let ac = Accounting::new_from_seperator("$", 2, ".", ",");
-    println!(
-        "\n{:<5}{:<20}{:>25}{:>25}\n{:-^80}",
-        "ID", "BUDGET", "CURRENT FUNDS", "INITIAL FUNDS", ""
-    );
+pub fn sort_budgets(budgets: &mut [Budget], key: &SortKey) {
+    match key {
+        SortKey::Name => budgets.sort_by_key(|budget| budget.name.to_lowercase()),
+        SortKey::Current => budgets.sort_by(|a, b| b.current_funds.total_cmp(&a.current_funds)),
+        SortKey::Used => budgets.sort_by(|a, b| b.spent().total_cmp(&a.spent())),
+        SortKey::Percent => budgets.sort_by(|a, b| {
+            let percent = |budget: &Budget| budget.percent_used().unwrap_or(f64::NEG_INFINITY);
+            percent(b).total_cmp(&percent(a))
+        }),
+    }
+}
+
+pub fn progress_bar(percent: Option<f64>, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let Some(percent) = percent else {
+        return " ".repeat(width);
+    };
+    let eighths = (percent.clamp(0.0, 100.0) / 100.0 * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(PARTIAL[eighths % 8]);
+    }
+    let filled = bar.chars().count();
+    bar + &" ".repeat(width - filled)
+}
+
+fn format_percent(percent: Option<f64>) -> String {
+    match percent {
+        Some(percent) => format!("{:.0}%", percent),
+        None => String::from("-"),
+    }
+}
+
+pub fn print_budgets(
+    budgets: &Vec<Budget>,
+    depths: &HashMap<u32, usize>,
+    trends: &HashMap<u32, Vec<f64>>,
+    forecasts: Option<&HashMap<u32, Forecast>>,
+    unassigned: Option<f64>,
+    config: &Config,
+) {
+    let threshold = config.warning_threshold;
+    if config.output != OutputFormat::Table {
+        let has_parents = budgets.iter().any(|budget| budget.parent_id.is_some());
+        let rows: Vec<Vec<Cell>> = budgets
+            .iter()
+            .map(|budget| {
+                let mut row = vec![
+                    Cell::optional_number(budget.budget_id.map(f64::from)),
+                    Cell::text(&budget.name),
+                    Cell::Text(config.currency_code(&budget.currency)),
+                    Cell::Number(budget.current_funds),
+                    Cell::Number(budget.initial_funds),
+                    Cell::Number(budget.spent()),
+                    Cell::optional_number(budget.percent_used()),
+                    Cell::text(budget.status(&threshold).value()),
+                ];
+                if has_parents {
+                    row.push(Cell::optional_number(budget.parent_id.map(f64::from)));
+                }
+                if let Some(forecasts) = forecasts {
+                    let exhaustion = budget
+                        .budget_id
+                        .and_then(|id| forecasts.get(&id)?.exhaustion)
+                        .map(|date| date.to_string());
+                    row.push(Cell::optional_text(&exhaustion));
+                }
+                row
+            })
+            .collect();
+        let mut headers = vec![
+            "id",
+            "budget",
+            "currency",
+            "current_funds",
+            "initial_funds",
+            "spent",
+            "percent_used",
+            "status",
+        ];
+        if has_parents {
+            headers.push("parent_id");
+        }
+        if forecasts.is_some() {
+            headers.push("runs_out");
+        }
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let mut columns = vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::left("CUR").keep(),
+        Column::right("CURRENT FUNDS"),
+        Column::right("INITIAL FUNDS"),
+        Column::right("SPENT"),
+        Column::right("USED"),
+        Column::left("").keep(),
+        Column::left("STATUS").keep(),
+        Column::left("TREND").keep(),
+    ];
+    if forecasts.is_some() {
+        columns.push(Column::left("RUNS OUT").keep());
+    }
+    let mut table = Table::new(columns);
+    table.set_header_style(config.style(Role::Header));
     for budget in budgets {
+        let money = config.money_format(&budget.currency);
+        let status = budget.status(&threshold);
+        let depth = depths
+            .get(&budget.budget_id.unwrap())
+            .copied()
+            .unwrap_or_default();
+        let mut row = vec![
+            budget.budget_id.unwrap().to_string(),
+            tree_name(&budget.name, depth),
+            config.currency_code(&budget.currency),
+            money.format(budget.current_funds),
+            money.format(budget.initial_funds),
+            money.format(budget.spent()),
+            format_percent(budget.percent_used()),
+            progress_bar(budget.percent_used(), 20),
+            status.value().to_string(),
+            trends
+                .get(&budget.budget_id.unwrap())
+                .map(|trend| sparkline(trend))
+                .unwrap_or_default(),
+        ];
+        if let Some(forecasts) = forecasts {
+            row.push(
+                forecasts
+                    .get(&budget.budget_id.unwrap())
+                    .map(|forecast| format_exhaustion(forecast, config))
+                    .unwrap_or_default(),
+            );
+        }
+        table.add_row(row);
+        table.style_cell(3, config.amount_style(budget.current_funds));
+        table.style_cell(7, config.style(status.role()));
+        table.style_cell(8, config.style(status.role()));
+    }
+    let mut codes: Vec<String> = budgets
+        .iter()
+        .map(|budget| config.currency_code(&budget.currency))
+        .collect();
+    codes.sort();
+    codes.dedup();
+    if !codes.is_empty() {
+        table.add_separator();
+    }
+    for code in codes {
+        let mut total = Budget::new("Total", &0.0);
+        let roots = budgets.iter().filter(|budget| {
+            budget
+                .budget_id
+                .is_none_or(|id| depths.get(&id).copied().unwrap_or_default() == 0)
+        });
+        for budget in roots {
+            if config.currency_code(&budget.currency) == code {
+                total.initial_funds += budget.initial_funds;
+                total.current_funds += budget.current_funds;
+            }
+        }
+        let money = config.money_format(&Some(code.to_owned()));
+        table.add_row(vec![
+            String::new(),
+            String::from("TOTAL"),
+            code,
+            money.format(total.current_funds),
+            money.format(total.initial_funds),
+            money.format(total.spent()),
+            format_percent(total.percent_used()),
+            progress_bar(total.percent_used(), 20),
+        ]);
+        table.style_cell(3, config.amount_style(total.current_funds));
+    }
+    table.print();
+    if let Some(unassigned) = unassigned {
+        let amount = config.money_format(&None).format(unassigned);
         println!(
-            "{:<5}{:<20}{:>25}{:>25}",
-            budget.budget_id.unwrap(),
-            budget.name,
-            ac.format_money(budget.current_funds),
-            ac.format_money(budget.initial_funds)
-        )
+            "\nTo be budgeted: {}",
+            paint(&amount, config.amount_style(unassigned))
+        );
     }
 }
 
+pub fn print_converted_budgets(
+    budgets: &[Budget],
+    depths: &HashMap<u32, usize>,
+    rates: &[f64],
+    code: &str,
+    config: &Config,
+) {
+    let target = Some(code.to_string());
+    let depth = |budget: &Budget| {
+        budget
+            .budget_id
+            .and_then(|id| depths.get(&id).copied())
+            .unwrap_or_default()
+    };
+    let total: f64 = budgets
+        .iter()
+        .zip(rates)
+        .filter(|(budget, _)| depth(budget) == 0)
+        .map(|(budget, rate)| budget.current_funds * rate)
+        .sum();
+    if config.output != OutputFormat::Table {
+        let mut rows: Vec<Vec<Cell>> = budgets
+            .iter()
+            .zip(rates)
+            .map(|(budget, rate)| {
+                vec![
+                    Cell::optional_number(budget.budget_id.map(f64::from)),
+                    Cell::text(&budget.name),
+                    Cell::Text(config.currency_code(&budget.currency)),
+                    Cell::Number(budget.current_funds),
+                    Cell::Number(*rate),
+                    Cell::Number(budget.current_funds * rate),
+                ]
+            })
+            .collect();
+        rows.push(vec![
+            Cell::Empty,
+            Cell::text("Total"),
+            Cell::text(code),
+            Cell::Empty,
+            Cell::Empty,
+            Cell::Number(total),
+        ]);
+        let headers = [
+            "id",
+            "budget",
+            "currency",
+            "current_funds",
+            "rate",
+            "converted_funds",
+        ];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let target_money = config.money_format(&target);
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::left("CURRENCY").keep(),
+        Column::right("CURRENT FUNDS"),
+        Column::right("RATE"),
+        Column::right(&format!("FUNDS IN {}", code)),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for (budget, rate) in budgets.iter().zip(rates) {
+        let money = config.money_format(&budget.currency);
+        table.add_row(vec![
+            budget.budget_id.unwrap().to_string(),
+            tree_name(&budget.name, depth(budget)),
+            config.currency_code(&budget.currency),
+            money.format(budget.current_funds),
+            format!("{:.4}", rate),
+            target_money.format(budget.current_funds * rate),
+        ]);
+        table.style_cell(3, config.amount_style(budget.current_funds));
+        table.style_cell(5, config.amount_style(budget.current_funds * rate));
+    }
+    table.add_separator();
+    table.add_row(vec![
+        String::new(),
+        String::from("TOTAL"),
+        code.to_string(),
+        String::new(),
+        String::new(),
+        target_money.format(total),
+    ]);
+    table.style_cell(5, config.amount_style(total));
+    table.print();
+}
+
 pub fn create_budget_table(db: &Connection) -> Result<usize, rusqlite::Error> {
     let query = "
         CREATE TABLE IF NOT EXISTS budgets (
@@ -72,13 +404,28 @@ pub fn create_budget_table(db: &Connection) -> Result<usize, rusqlite::Error> {
     db.execute(query, ())
 }
 
+pub fn add_currency_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute("ALTER TABLE budgets ADD COLUMN currency TEXT;", ())?;
+    Ok(())
+}
+
+pub fn add_threshold_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute("ALTER TABLE budgets ADD COLUMN warning_threshold REAL;", ())?;
+    Ok(())
+}
+
 pub fn insert_budget(db: &Connection, budget: &Budget) -> Result<usize, rusqlite::Error> {
     let query = "
-        INSERT INTO budgets (name, initial_funds, current_funds)
-        VALUES (?1, ?2, ?3);";
+        INSERT INTO budgets (name, initial_funds, current_funds, currency)
+        VALUES (?1, ?2, ?3, ?4);";
     db.execute(
         query,
-        (&budget.name, &budget.initial_funds, &budget.current_funds),
+        (
+            &budget.name,
+            &budget.initial_funds,
+            &budget.current_funds,
+            &budget.currency,
+        ),
     )
 }
 
@@ -93,6 +440,11 @@ pub fn select_budget_by_id(db: &Connection, id: &u32) -> Result<Vec<Budget>, rus
             name: row.get(1)?,
             initial_funds: row.get(2)?,
             current_funds: row.get(3)?,
+            currency: row.get(4)?,
+            warning_threshold: row.get(5)?,
+            goal: Goal::from_columns(row.get(6)?, row.get(7)?, row.get(8)?),
+            parent_id: row.get(9)?,
+            spending_cap: row.get(10)?,
         })
     })?;
     let budgets = vec![budget];
@@ -110,6 +462,11 @@ pub fn select_all_budgets(db: &Connection) -> Result<Vec<Budget>, rusqlite::Erro
             name: row.get(1)?,
             initial_funds: row.get(2)?,
             current_funds: row.get(3)?,
+            currency: row.get(4)?,
+            warning_threshold: row.get(5)?,
+            goal: Goal::from_columns(row.get(6)?, row.get(7)?, row.get(8)?),
+            parent_id: row.get(9)?,
+            spending_cap: row.get(10)?,
         })
     })?;
     let mut budgets = Vec::new();
@@ -124,14 +481,26 @@ pub fn update_budget(db: &Connection, budget: &Budget) -> Result<usize, rusqlite
         UPDATE budgets
         SET name = ?1,
             initial_funds = ?2,
-            current_funds = ?3
-        WHERE budget_id = ?4";
+            current_funds = ?3,
+            warning_threshold = ?4,
+            target_amount = ?5,
+            target_date = ?6,
+            goal_started = ?7,
+            parent_id = ?8,
+            spending_cap = ?9
+        WHERE budget_id = ?10";
     db.execute(
         query,
         (
             &budget.name,
             &budget.initial_funds,
             &budget.current_funds,
+            &budget.warning_threshold,
+            budget.goal.as_ref().map(|goal| goal.target),
+            budget.goal.as_ref().map(|goal| goal.deadline),
+            budget.goal.as_ref().map(|goal| goal.started),
+            &budget.parent_id,
+            &budget.spending_cap,
             &budget.budget_id,
         ),
     )
@@ -220,6 +589,39 @@ mod tests {
         assert_ne!(budget.initial_funds, 5000.0);
     }
 
+    #[test]
+    fn status_ok() {
+        let mut budget = Budget::new("Test", &100.0);
+        assert_eq!(budget.status(&80.0), Status::Healthy);
+        budget.reduce_funds(&85.0);
+        assert_eq!(budget.status(&80.0), Status::Warning);
+        budget.warning_threshold = Some(90.0);
+        assert_eq!(budget.status(&80.0), Status::Healthy);
+        budget.reduce_funds(&20.0);
+        assert_eq!(budget.status(&80.0), Status::Over);
+    }
+
+    #[test]
+    fn sort_budgets_ok() {
+        let mut food = Budget::new("Food", &100.0);
+        food.reduce_funds(&90.0);
+        let mut rent = Budget::new("Rent", &1000.0);
+        rent.reduce_funds(&500.0);
+        let mut budgets = vec![food, rent];
+        sort_budgets(&mut budgets, &SortKey::Used);
+        assert_eq!(budgets[0].name, "Rent");
+        sort_budgets(&mut budgets, &SortKey::Percent);
+        assert_eq!(budgets[0].name, "Food");
+    }
+
+    #[test]
+    fn progress_bar_ok() {
+        assert_eq!(progress_bar(Some(50.0), 4), "██  ");
+        assert_eq!(progress_bar(Some(150.0), 2), "██");
+        assert_eq!(progress_bar(Some(6.25), 2), "▏ ");
+        assert_eq!(progress_bar(None, 3), "   ");
+    }
+
     #[test]
     fn rename_ok() {
         let mut budget = Budget::new("Test", &5000.0);
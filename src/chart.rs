@@ -0,0 +1,324 @@
+use crate::budget::{progress_bar, Budget};
+use crate::color::Role;
+use crate::config::Config;
+use crate::dates::{to_utc, today};
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::reduction_actions;
+use crate::table::{terminal_width, Column, Table};
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const MAX_BAR_WIDTH: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    pub fn label(&self, date: NaiveDate) -> String {
+        match self {
+            Self::Day | Self::Week => date.format("%Y-%m-%d").to_string(),
+            Self::Month => date.format("%Y-%m").to_string(),
+        }
+    }
+
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Self::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    pub fn previous(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date - Days::new(1),
+            Self::Week => date - Days::new(7),
+            Self::Month => date - Months::new(1),
+        }
+    }
+
+    pub fn following(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date + Days::new(1),
+            Self::Week => date + Days::new(7),
+            Self::Month => date + Months::new(1),
+        }
+    }
+
+    pub fn year_before(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Days::new(364),
+            Self::Day | Self::Month => date - Months::new(12),
+        }
+    }
+}
+
+pub struct PeriodTotal {
+    pub period: String,
+    pub amount: f64,
+}
+
+pub fn get_spending_by_period(
+    db: &Connection,
+    id: &u32,
+    period: &Period,
+    count: &u32,
+    tz: &Option<Tz>,
+) -> Result<Vec<PeriodTotal>, rusqlite::Error> {
+    let mut start = period.start_of(today(tz));
+    let mut starts = Vec::new();
+    for _ in 0..*count {
+        starts.push(start);
+        start = period.previous(start);
+    }
+    starts.reverse();
+    let since = starts
+        .first()
+        .and_then(|first| first.and_hms_opt(0, 0, 0))
+        .and_then(|first| to_utc(&first, tz))
+        .unwrap_or_default();
+    let query = format!(
+        "
+        SELECT created_at, amount
+        FROM records
+        WHERE budget_id = ?1 AND action IN {} AND pending = 0 AND created_at >= ?2;",
+        reduction_actions()
+    );
+    let mut stmt = db.prepare(&query)?;
+    let rows = stmt.query_map((id, since), |row| {
+        Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, f64>(1)?))
+    })?;
+    let mut totals: HashMap<NaiveDate, f64> = HashMap::new();
+    for row in rows {
+        let (created_at, amount) = row?;
+        let date = match tz {
+            Some(tz) => created_at.with_timezone(tz).date_naive(),
+            None => created_at.with_timezone(&Local).date_naive(),
+        };
+        *totals.entry(period.start_of(date)).or_default() += amount;
+    }
+    let periods = starts
+        .into_iter()
+        .map(|start| PeriodTotal {
+            period: period.label(start),
+            amount: totals.get(&start).copied().unwrap_or_default(),
+        })
+        .collect();
+    Ok(periods)
+}
+
+pub fn get_balance_trends(
+    db: &Connection,
+    points: usize,
+) -> Result<HashMap<u32, Vec<f64>>, rusqlite::Error> {
+    let query = "
+        SELECT budget_id, balance_after
+        FROM (
+            SELECT budget_id, balance_after, created_at, record_id, ROW_NUMBER() OVER (
+                PARTITION BY budget_id ORDER BY created_at DESC, record_id DESC
+            ) AS n
+            FROM records
+            WHERE balance_after IS NOT NULL AND pending = 0
+        )
+        WHERE n <= ?1
+        ORDER BY created_at, record_id;";
+    let mut stmt = db.prepare(query)?;
+    let rows = stmt.query_map([points], |row| Ok((row.get::<_, u32>(0)?, row.get(1)?)))?;
+    let mut trends: HashMap<u32, Vec<f64>> = HashMap::new();
+    for row in rows {
+        let (budget_id, balance) = row?;
+        trends.entry(budget_id).or_default().push(balance);
+    }
+    Ok(trends)
+}
+
+pub fn sparkline(values: &[f64]) -> String {
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if high <= low {
+                return SPARKS[SPARKS.len() / 2];
+            }
+            let level = ((value - low) / (high - low) * (SPARKS.len() - 1) as f64).round();
+            SPARKS[level as usize]
+        })
+        .collect()
+}
+
+fn bar_width(reserved: usize) -> usize {
+    terminal_width()
+        .saturating_sub(reserved)
+        .clamp(10, MAX_BAR_WIDTH)
+}
+
+pub fn print_spending_chart(
+    budget: &Budget,
+    totals: &[PeriodTotal],
+    period: &Period,
+    config: &Config,
+) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = totals
+            .iter()
+            .map(|total| vec![Cell::text(&total.period), Cell::Number(total.amount)])
+            .collect();
+        return print_rows(&config.output, &[period.value(), "spent"], &rows);
+    }
+    let money = config.money_format(&budget.currency);
+    let high = totals.iter().map(|total| total.amount).fold(0.0, f64::max);
+    let width = bar_width(40);
+    println!("\nSpending per {} in {}", period.value(), budget.name);
+    let mut table = Table::new(vec![
+        Column::left(&period.value().to_uppercase()).keep(),
+        Column::left("").keep(),
+        Column::right("SPENT"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for total in totals {
+        let percent = (high > 0.0).then(|| total.amount / high * 100.0);
+        table.add_row(vec![
+            total.period.to_owned(),
+            progress_bar(percent, width),
+            money.format(total.amount),
+        ]);
+        table.style_cell(1, config.style(Role::Negative));
+    }
+    table.print();
+}
+
+pub fn print_breakdown_chart(budgets: &[Budget], config: &Config) {
+    let threshold = config.warning_threshold;
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = budgets
+            .iter()
+            .map(|budget| {
+                vec![
+                    Cell::optional_number(budget.budget_id.map(f64::from)),
+                    Cell::text(&budget.name),
+                    Cell::Number(budget.spent()),
+                    Cell::Number(budget.current_funds),
+                    Cell::optional_number(budget.percent_used()),
+                ]
+            })
+            .collect();
+        let headers = ["id", "budget", "spent", "remaining", "percent_used"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let width = bar_width(60);
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::left("USED / REMAINING").keep(),
+        Column::right("SPENT"),
+        Column::right("REMAINING"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for budget in budgets {
+        let money = config.money_format(&budget.currency);
+        let used = progress_bar(budget.percent_used(), width);
+        let filled = used.trim_end().chars().count();
+        let remaining = "░".repeat(width - filled);
+        table.add_row(vec![
+            budget.budget_id.unwrap().to_string(),
+            budget.name.to_owned(),
+            format!("{}{}", used.trim_end(), remaining),
+            money.format(budget.spent()),
+            money.format(budget.current_funds),
+        ]);
+        table.style_cell(2, config.style(budget.status(&threshold).role()));
+        table.style_cell(4, config.amount_style(budget.current_funds));
+    }
+    table.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::insert_budget;
+    use crate::database::migrate;
+
+    #[test]
+    fn sparkline_ok() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
+        assert_eq!(sparkline(&[3.0, 3.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn get_spending_by_period_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_budget(&db, &Budget::new("Food", &100.0)).unwrap();
+        let now = Utc::now();
+        let last_month = now - Months::new(1);
+        db.execute(
+            "INSERT INTO records (budget_id, action, amount, created_at, pending)
+            VALUES (1, 'Reduce funds', 10.0, ?1, 0), (1, 'Reduce funds', 5.0, ?1, 0),
+            (1, 'Increase funds', 99.0, ?1, 0), (1, 'Reduce funds', 7.0, ?2, 0);",
+            (now, last_month),
+        )
+        .unwrap();
+        let tz = Some(Tz::UTC);
+        let totals = get_spending_by_period(&db, &1, &Period::Month, &3, &tz).unwrap();
+        let amounts: Vec<f64> = totals.iter().map(|total| total.amount).collect();
+        assert_eq!(amounts, [0.0, 7.0, 15.0]);
+        assert_eq!(totals[2].period, now.format("%Y-%m").to_string());
+    }
+
+    #[test]
+    fn get_spending_by_period_tz_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_budget(&db, &Budget::new("Food", &100.0)).unwrap();
+        let tz = Some(Tz::Pacific__Kiritimati);
+        let morning = today(&tz).and_hms_opt(1, 0, 0).unwrap();
+        let created_at = to_utc(&morning, &tz).unwrap();
+        db.execute(
+            "INSERT INTO records (budget_id, action, amount, created_at, pending)
+            VALUES (1, 'Reduce funds', 10.0, ?1, 0), (1, 'Transfer out', 5.0, ?1, 0),
+            (1, 'Transfer in', 7.0, ?1, 0);",
+            [created_at],
+        )
+        .unwrap();
+        let totals = get_spending_by_period(&db, &1, &Period::Day, &2, &tz).unwrap();
+        let amounts: Vec<f64> = totals.iter().map(|total| total.amount).collect();
+        assert_eq!(amounts, [0.0, 15.0]);
+        assert_eq!(totals[1].period, today(&tz).format("%Y-%m-%d").to_string());
+    }
+
+    #[test]
+    fn get_balance_trends_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_budget(&db, &Budget::new("Food", &100.0)).unwrap();
+        db.execute(
+            "INSERT INTO records (budget_id, action, amount, created_at, pending, balance_after)
+            VALUES (1, 'Reduce funds', 10.0, '2024-03-01T10:00:00Z', 0, 90.0),
+            (1, 'Reduce funds', 10.0, '2024-03-03T10:00:00Z', 0, 70.0),
+            (1, 'Reduce funds', 10.0, '2024-03-02T10:00:00Z', 0, 80.0),
+            (1, 'Reduce funds', 10.0, '2024-03-04T10:00:00Z', 1, 60.0);",
+            (),
+        )
+        .unwrap();
+        let trends = get_balance_trends(&db, 2).unwrap();
+        assert_eq!(trends[&1], [80.0, 70.0]);
+    }
+}
@@ -1,75 +1,309 @@
-use crate::budget::{create_budget_table, print_budgets};
-use crate::record::{create_record_table, print_records};
+use crate::account::{print_accounts, AccountKind};
+use crate::audit::print_audit_entries;
+use crate::budget::{print_budgets, print_converted_budgets, SortKey, Status};
+use crate::chart::{print_breakdown_chart, print_spending_chart, Period};
+use crate::color::{paint, ColorChoice, Role};
+use crate::config::{is_valid_threshold, is_valid_window, Config};
+use crate::currency::parse_currency_code;
+use crate::database::migrate;
+use crate::dates::{
+    current_month, parse_as_of, parse_date, parse_day, parse_deadline, parse_month, parse_timezone,
+    today, DateInput,
+};
+use crate::error::Error;
+use crate::expression::{evaluate, is_expression, Expression};
+use crate::forecast::{print_forecasts, Weighting};
+use crate::goal::print_goals;
+use crate::journal::print_operations;
+use crate::output::OutputFormat;
+use crate::payee::{print_payee_report, print_payees};
+use crate::rates::print_rates;
+use crate::reconcile::print_reconciliation;
+use crate::record::print_records;
+use crate::report::{print_monthly_report, print_variance_report, Comparison};
 use crate::services::{
-    create_budget, get_budgets, get_history, increase_funds, reduce_funds, remove_budget,
-    rename_budget, reset_funds, set_current_funds, set_initial_funds,
+    add_income, add_payee_alias, assign_income, backup_database, create_account, create_budget,
+    delete_split, edit_split, get_accounts, get_audit_log, get_budget, get_budget_list,
+    get_budget_tree, get_budgets_as_of, get_conversion_rates, get_exchange_rates, get_forecasts,
+    get_goals, get_history, get_monthly_report, get_payee_report, get_payees, get_spending,
+    get_trends, get_unassigned, get_variance_report, import_exchange_rates, increase_funds,
+    reconcile_account, reconcile_budget, redo, reduce_funds, reduce_split, remove_account,
+    remove_budget, rename_budget, reset_funds, restore_database, set_current_funds,
+    set_exchange_rate, set_goal, set_initial_funds, set_parent, set_spending_cap,
+    set_warning_threshold, settle_pending_records, take_snapshot, transfer_funds, undo,
 };
-use clap::{Parser, Subcommand};
+use crate::split::parse_split;
+use crate::timeline::print_budget_states;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::{generate, Shell};
 use rusqlite::Connection;
+use std::fmt::Display;
+use std::io;
 use std::path::PathBuf;
 use std::process;
 
 #[derive(Subcommand)]
 pub enum Command {
+    /// Print audit log of budget changes
+    Audit {
+        #[arg(long, short, value_name = "ID")]
+        budget: Option<u32>,
+    },
+    /// Create or remove bank, cash and credit accounts
+    Account {
+        #[command(subcommand)]
+        action: AccountAction,
+    },
+    /// List accounts with net worth and money not allocated to budgets
+    Accounts,
+    /// Move unassigned income into a budget
+    Assign {
+        #[arg(value_name = "ID")]
+        id: u32,
+        #[arg(value_name = "AMOUNT")]
+        amount: String,
+        /// Add small description
+        #[arg(long, short, value_name = "DESCRIPTION")]
+        description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+    },
+    /// Back up the database
+    Backup {
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+    },
+    /// Set or clear the most that budgets below a parent may spend in total
+    Cap {
+        #[arg(value_name = "ID")]
+        id: u32,
+        /// Amount, omit to remove the cap
+        #[arg(value_name = "AMOUNT")]
+        amount: Option<String>,
+    },
+    /// Draw charts of spending and budget usage
+    Chart {
+        #[command(subcommand)]
+        view: ChartView,
+    },
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_name = "SHELL")]
+        shell: Shell,
+    },
+    /// Show or change configuration settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
     /// Set current budget funds
     Current {
         #[arg(value_name = "ID")]
         id: u32,
         #[arg(value_name = "AMOUNT")]
-        amount: f64,
+        amount: String,
         /// Add small description
         #[arg(long, short, value_name = "DESCRIPTION")]
         description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+    },
+    /// Estimate burn rate, when funds run out and a safe daily spend
+    Forecast {
+        #[arg(value_name = "ID")]
+        id: Option<u32>,
+        /// Number of past days used to estimate the burn rate
+        #[arg(long, short, value_name = "DAYS", value_parser = parse_window)]
+        window: Option<u32>,
+        /// How recent days are weighted against older ones
+        #[arg(long, value_name = "WEIGHTING")]
+        weighting: Option<Weighting>,
     },
+    /// Set or clear a savings goal for a budget
+    Goal {
+        #[arg(value_name = "ID")]
+        id: u32,
+        #[arg(value_name = "TARGET", required_unless_present = "clear")]
+        target: Option<String>,
+        /// Date to reach the target by: YYYY-MM-DD, or YYYY-MM for the end of that month
+        #[arg(value_name = "DEADLINE", value_parser = parse_deadline, required_unless_present = "clear")]
+        deadline: Option<NaiveDate>,
+        /// Remove the goal from the budget
+        #[arg(long, conflicts_with_all = ["target", "deadline"])]
+        clear: bool,
+    },
+    /// Show progress towards savings goals
+    Goals,
     /// Print transaction history
     History {
         #[arg(value_name = "ID")]
         id: Option<u32>,
+        /// Number of records to show
+        #[arg(long, short, value_name = "N")]
+        limit: Option<u32>,
+        /// Include the records of every budget below this one
+        #[arg(long, short, requires = "id")]
+        tree: bool,
     },
     /// Set initial budget funds
     Initial {
         #[arg(value_name = "ID")]
         id: u32,
         #[arg(value_name = "AMOUNT")]
-        amount: f64,
+        amount: String,
         /// Add small description
         #[arg(long, short, value_name = "DESCRIPTION")]
         description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
     },
     /// Increase budget funds
     Increase {
         #[arg(value_name = "ID")]
         id: u32,
         #[arg(value_name = "AMOUNT")]
-        amount: f64,
+        amount: String,
         /// Add small description
         #[arg(long, short, value_name = "DESCRIPTION")]
         description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+        /// Account the money moves in or out of
+        #[arg(long, short, value_name = "ACCOUNT ID")]
+        account: Option<u32>,
+        /// Who was paid or paid in, matched against known payees and their aliases
+        #[arg(long, short, value_name = "PAYEE")]
+        payee: Option<String>,
+    },
+    /// Deposit income that is not yet assigned to a budget
+    Income {
+        #[arg(value_name = "AMOUNT")]
+        amount: String,
+        /// Add small description
+        #[arg(long, short, value_name = "DESCRIPTION")]
+        description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+        /// Account the money moves in or out of
+        #[arg(long, short, value_name = "ACCOUNT ID")]
+        account: Option<u32>,
     },
     /// List all budgets
-    List,
+    List {
+        /// Show balances as they were at a past date
+        #[arg(long, value_name = "DATE", value_parser = parse_as_of)]
+        as_of: Option<DateInput>,
+        /// Convert balances to this currency and show a grand total
+        #[arg(long = "in", value_name = "CODE", value_parser = parse_currency_code, conflicts_with = "as_of")]
+        currency: Option<String>,
+        /// Sort by name, or by current funds, amount spent or percent used with the largest first
+        #[arg(long, value_name = "KEY", conflicts_with = "as_of")]
+        sort: Option<SortKey>,
+        /// Only show budgets with this status
+        #[arg(long, value_name = "STATUS", conflicts_with = "as_of")]
+        filter: Option<Status>,
+        /// Only show budgets whose name contains this text or matches a pattern such as 'trip*'
+        #[arg(long, short, value_name = "PATTERN", conflicts_with = "as_of")]
+        name: Option<String>,
+        /// Add a column with the projected date each budget runs out
+        #[arg(long, conflicts_with_all = ["as_of", "currency"])]
+        forecast: bool,
+    },
     /// Create a new budget
     New {
         #[arg(value_name = "NAME")]
         name: String,
         #[arg(value_name = "FUNDS")]
-        funds: f64,
+        funds: String,
+        /// Currency code, defaults to the configured base currency
+        #[arg(long, short, value_name = "CODE", value_parser = parse_currency_code)]
+        currency: Option<String>,
     },
-    /// Reduce budget funds
-    Reduce {
+    /// Place a budget under a parent budget
+    Parent {
         #[arg(value_name = "ID")]
         id: u32,
-        #[arg(value_name = "AMOUNT")]
-        amount: f64,
+        /// Parent budget, omit to make it a top level budget
+        #[arg(value_name = "PARENT ID")]
+        parent: Option<u32>,
+    },
+    /// Manage exchange rates between currencies
+    Rates {
+        #[command(subcommand)]
+        action: RatesAction,
+    },
+    /// Reconcile a budget or account against the closing balance of a statement
+    Reconcile {
+        #[arg(value_name = "ID", required_unless_present = "account")]
+        id: Option<u32>,
+        /// Reconcile an account instead of a budget
+        #[arg(long, short, value_name = "ACCOUNT ID", conflicts_with = "id")]
+        account: Option<u32>,
+        /// Closing balance on the statement
+        #[arg(long, value_name = "AMOUNT", allow_hyphen_values = true)]
+        statement_balance: String,
+        /// Statement date, later records are left out
+        #[arg(long, value_name = "DATE", value_parser = parse_as_of)]
+        as_of: Option<DateInput>,
+        /// Mark records as cleared, separate several IDs with commas
+        #[arg(long, value_name = "RECORD ID", value_delimiter = ',')]
+        clear: Vec<u32>,
+        /// Mark cleared records as uncleared again
+        #[arg(long, value_name = "RECORD ID", value_delimiter = ',')]
+        unclear: Vec<u32>,
+        /// Lock the cleared records once the difference is zero
+        #[arg(long)]
+        finish: bool,
+    },
+    /// List payees or give them aliases
+    Payee {
+        #[command(subcommand)]
+        action: PayeeAction,
+    },
+    /// Redo the last undone operations
+    Redo {
+        #[arg(value_name = "N", default_value_t = 1)]
+        count: u32,
+    },
+    /// Reduce budget funds
+    Reduce {
+        #[arg(value_name = "ID", required_unless_present = "split")]
+        id: Option<u32>,
+        #[arg(value_name = "AMOUNT", required_unless_present = "split")]
+        amount: Option<String>,
         /// Add small description
         #[arg(long, short, value_name = "DESCRIPTION")]
         description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+        /// Split one transaction across budgets, repeat for each part
+        #[arg(long, value_name = "ID:AMOUNT", value_parser = parse_split, conflicts_with_all = ["id", "amount"])]
+        split: Vec<(u32, String)>,
+        /// Account the money moves in or out of
+        #[arg(long, short, value_name = "ACCOUNT ID")]
+        account: Option<u32>,
+        /// Who was paid or paid in, matched against known payees and their aliases
+        #[arg(long, short, value_name = "PAYEE")]
+        payee: Option<String>,
     },
     /// Remove a budget
     Remove {
         #[arg(value_name = "ID")]
         id: u32,
     },
+    /// Restore the database from a backup
+    Restore {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
     /// Rename a budget
     Rename {
         #[arg(value_name = "ID")]
@@ -77,6 +311,11 @@ pub enum Command {
         #[arg(value_name = "NEW NAME")]
         name: String,
     },
+    /// Summarise budget activity over a period
+    Report {
+        #[command(subcommand)]
+        kind: ReportKind,
+    },
     /// Reset a budget to initial funds
     Reset {
         #[arg(value_name = "ID")]
@@ -84,6 +323,200 @@ pub enum Command {
         /// Add small description
         #[arg(long, short, value_name = "DESCRIPTION")]
         description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+    },
+    /// Set the percent used at which a budget shows a warning
+    Threshold {
+        #[arg(value_name = "ID")]
+        id: u32,
+        /// Percent between 0 and 100, omit to use the configured default
+        #[arg(value_name = "PERCENT", value_parser = parse_threshold)]
+        percent: Option<f64>,
+    },
+    /// Edit or delete a split transaction and all its parts
+    Split {
+        #[command(subcommand)]
+        action: SplitAction,
+    },
+    /// Move funds between budgets, converting currencies if needed
+    Transfer {
+        #[arg(value_name = "FROM ID")]
+        from: u32,
+        #[arg(value_name = "TO ID")]
+        to: u32,
+        #[arg(value_name = "AMOUNT")]
+        amount: String,
+        /// Exchange rate to use instead of the stored one
+        #[arg(long, value_name = "RATE")]
+        rate: Option<f64>,
+        /// Add small description
+        #[arg(long, short, value_name = "DESCRIPTION")]
+        description: Option<String>,
+        /// Effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+    },
+    /// Undo the last operations
+    Undo {
+        #[arg(value_name = "N", default_value_t = 1)]
+        count: u32,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ChartView {
+    /// Bar chart of a budget's reductions per day, week or month
+    Spending {
+        #[arg(value_name = "ID")]
+        id: u32,
+        /// Length of each bar's period
+        #[arg(long, short, value_name = "PERIOD", default_value = "week")]
+        by: Period,
+        /// Number of periods to show, ending with the current one
+        #[arg(long, short, value_name = "N", default_value_t = 12)]
+        periods: u32,
+    },
+    /// Compare used and remaining funds across budgets
+    Breakdown,
+}
+
+#[derive(Subcommand)]
+pub enum ReportKind {
+    /// Opening and closing balances, increases, reductions and resets for a month
+    Monthly {
+        /// Month to report, defaults to the current one
+        #[arg(long, short, value_name = "YYYY-MM", value_parser = parse_month)]
+        month: Option<NaiveDate>,
+        /// Number of descriptions listed per budget
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        top: u32,
+    },
+    /// Total spending per payee over a period, the current month by default
+    Payees {
+        /// First day of the period
+        #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_day, requires = "to")]
+        from: Option<NaiveDate>,
+        /// Last day of the period
+        #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_day, requires = "from")]
+        to: Option<NaiveDate>,
+    },
+    /// Compare each budget's spending in the current period with an earlier one
+    Variance {
+        /// Length of the compared periods
+        #[arg(long, short, value_name = "PERIOD", default_value = "month")]
+        period: Period,
+        /// Compare with the previous period or the same period a year ago
+        #[arg(long, short, value_name = "BASELINE", default_value = "previous")]
+        compare: Comparison,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print a configuration value
+    Get {
+        #[arg(value_name = "KEY")]
+        key: String,
+    },
+    /// Change a configuration value, an empty value clears it
+    Set {
+        #[arg(value_name = "KEY")]
+        key: String,
+        #[arg(value_name = "VALUE")]
+        value: String,
+    },
+    /// Print all configuration values
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum RatesAction {
+    /// Import exchange rates from a CSV file with FROM,TO,RATE lines
+    Import {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+    /// List stored exchange rates
+    List,
+    /// Set the rate of one unit of FROM in TO
+    Set {
+        #[arg(value_name = "FROM", value_parser = parse_currency_code)]
+        from: String,
+        #[arg(value_name = "TO", value_parser = parse_currency_code)]
+        to: String,
+        #[arg(value_name = "RATE")]
+        rate: f64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AccountAction {
+    /// Create a new account
+    New {
+        #[arg(value_name = "NAME")]
+        name: String,
+        /// Opening balance, negative for money owed on a credit account
+        #[arg(
+            value_name = "BALANCE",
+            default_value = "0",
+            allow_hyphen_values = true
+        )]
+        balance: String,
+        /// Type of account
+        #[arg(long, short, value_name = "KIND", default_value = "bank")]
+        kind: AccountKind,
+        /// Currency code, defaults to the configured base currency
+        #[arg(long, short, value_name = "CODE", value_parser = parse_currency_code)]
+        currency: Option<String>,
+    },
+    /// Remove an account that no record refers to
+    Remove {
+        #[arg(value_name = "ACCOUNT ID")]
+        id: u32,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PayeeAction {
+    /// List known payees, most used first
+    List {
+        /// Only show payees starting with this text
+        #[arg(value_name = "PREFIX")]
+        prefix: Option<String>,
+    },
+    /// Make another spelling resolve to a payee
+    Alias {
+        #[arg(value_name = "PAYEE ID")]
+        id: u32,
+        #[arg(value_name = "ALIAS")]
+        alias: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SplitAction {
+    /// Change the description or date of every part
+    Edit {
+        #[arg(value_name = "SPLIT ID")]
+        id: u32,
+        /// New description
+        #[arg(
+            long,
+            short,
+            value_name = "DESCRIPTION",
+            required_unless_present = "date"
+        )]
+        description: Option<String>,
+        /// New effective date: YYYY-MM-DD[THH:MM], today, yesterday or an offset such as -3d
+        #[arg(long, value_name = "DATE", value_parser = parse_date, allow_hyphen_values = true)]
+        date: Option<DateInput>,
+    },
+    /// Delete every part and give the funds back to their budgets
+    Delete {
+        #[arg(value_name = "SPLIT ID")]
+        id: u32,
     },
 }
 
@@ -94,29 +527,109 @@ impl Command {
                 id: _,
                 amount: _,
                 description: _,
+                date: _,
             } => "Set current funds",
             Self::Increase {
                 id: _,
                 amount: _,
                 description: _,
+                date: _,
+                account: _,
+                payee: _,
             } => "Increase funds",
             Self::Initial {
                 id: _,
                 amount: _,
                 description: _,
+                date: _,
             } => "Set initial funds",
+            Self::New {
+                name: _,
+                funds: _,
+                currency: _,
+            } => "Create budget",
             Self::Reduce {
                 id: _,
                 amount: _,
                 description: _,
+                date: _,
+                split: _,
+                account: _,
+                payee: _,
             } => "Reduce funds",
+            Self::Account {
+                action: AccountAction::New { .. },
+            } => "Create account",
+            Self::Account {
+                action: AccountAction::Remove { .. },
+            } => "Remove account",
+            Self::Split {
+                action: SplitAction::Edit { .. },
+            } => "Edit split",
+            Self::Split {
+                action: SplitAction::Delete { .. },
+            } => "Delete split",
+            Self::Payee {
+                action: PayeeAction::Alias { .. },
+            } => "Add payee alias",
+            Self::Reconcile { .. } => "Reconcile",
+            Self::Undo { .. } => "Undo",
+            Self::Redo { .. } => "Redo",
+            Self::Remove { id: _ } => "Remove budget",
+            Self::Rename { id: _, name: _ } => "Rename budget",
+            Self::Threshold { id: _, percent: _ } => "Set threshold",
+            Self::Parent { id: _, parent: _ } => "Set parent",
+            Self::Cap { id: _, amount: _ } => "Set cap",
+            Self::Income {
+                amount: _,
+                description: _,
+                date: _,
+                account: _,
+            } => "Add income",
+            Self::Assign {
+                id: _,
+                amount: _,
+                description: _,
+                date: _,
+            } => "Assign funds",
+            Self::Goal {
+                id: _,
+                target: _,
+                deadline: _,
+                clear: _,
+            } => "Set goal",
+            Self::Transfer {
+                from: _,
+                to: _,
+                amount: _,
+                rate: _,
+                description: _,
+                date: _,
+            } => "Transfer funds",
             Self::Reset {
                 id: _,
                 description: _,
+                date: _,
             } => "Reset funds",
             _ => "",
         }
     }
+
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            Self::Current { .. }
+                | Self::Initial { .. }
+                | Self::Remove { .. }
+                | Self::Account {
+                    action: AccountAction::Remove { .. }
+                }
+                | Self::Restore { .. }
+                | Self::Split {
+                    action: SplitAction::Delete { .. }
+                }
+        )
+    }
 }
 
 #[derive(Parser)]
@@ -126,77 +639,735 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
     /// Select a database file
-    #[arg(long, short, value_name = "FILE NAME")]
+    #[arg(long, short, value_name = "FILE NAME", env = "BUDGET_DB")]
     pub database: Option<PathBuf>,
+    /// Keep this many automatic snapshots taken before destructive commands
+    #[arg(long, value_name = "COUNT")]
+    pub snapshots: Option<usize>,
+    /// Name recorded in the audit log
+    #[arg(long, value_name = "NAME", env = "BUDGET_ACTOR")]
+    pub actor: Option<String>,
+    /// Timezone used to display and enter dates, such as Europe/Madrid
+    #[arg(long, value_name = "ZONE", value_parser = parse_timezone)]
+    pub tz: Option<Tz>,
+    /// Output format for listings
+    #[arg(long, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+    /// Colour the output
+    #[arg(long, value_name = "WHEN")]
+    pub color: Option<ColorChoice>,
+}
+
+impl Cli {
+    pub fn load_config(&self) -> Result<Config, Error> {
+        let mut config = Config::load()?;
+        if let Some(database) = &self.database {
+            config.database = Some(database.to_owned());
+        }
+        if let Some(snapshots) = self.snapshots {
+            config.snapshots = snapshots;
+        }
+        if let Some(actor) = &self.actor {
+            config.actor = Some(actor.to_owned());
+        }
+        if let Some(tz) = self.tz {
+            config.timezone = Some(tz.name().to_string());
+        }
+        if let Some(output) = self.output {
+            config.output = output;
+        }
+        if let Some(color) = self.color {
+            config.color = color;
+        }
+        Ok(config)
+    }
+}
+
+fn parse_threshold(text: &str) -> Result<f64, String> {
+    match text.trim_end_matches('%').parse::<f64>() {
+        Ok(threshold) if is_valid_threshold(&threshold) => Ok(threshold),
+        _ => Err(format!("invalid threshold '{}', expected 0 to 100", text)),
+    }
+}
+
+fn parse_window(text: &str) -> Result<u32, String> {
+    match text.parse::<u32>() {
+        Ok(window) if is_valid_window(&window) => Ok(window),
+        _ => Err(format!("invalid window '{}', expected 1 to 365 days", text)),
+    }
+}
+
+fn resolve_date(date: &DateInput, tz: &Option<Tz>) -> DateTime<Utc> {
+    match date.resolve(tz) {
+        Some(date) => date,
+        None => {
+            eprintln!("Error: date does not exist in the selected timezone.");
+            process::exit(1);
+        }
+    }
 }
 
-pub fn run(db: Connection, command: Command) {
-    if let Err(error) = create_budget_table(&db) {
-        eprintln!("Error: {}.", error);
+fn parse_amount(text: &str, config: &Config) -> f64 {
+    match config.parse_amount(text) {
+        Ok(amount) => amount,
+        Err(error) => {
+            print_error(config, format!("{}.", error));
+            process::exit(1);
+        }
+    }
+}
+
+fn positive_amount(text: &str, config: &Config) -> f64 {
+    let amount = parse_amount(text, config);
+    if amount <= 0.0 {
+        print_error(config, "amount must be greater than zero.");
         process::exit(1);
     }
+    amount
+}
 
-    if let Err(error) = create_record_table(&db) {
-        eprintln!("Error: {}.", error);
+fn evaluate_amount(text: &str, config: &Config) -> f64 {
+    if !is_expression(text) {
+        return parse_amount(text, config);
+    }
+    match evaluate(text, &config.input_locale(), None) {
+        Ok(amount) => amount,
+        Err(error) => {
+            print_error(config, format!("{}.", error));
+            process::exit(1);
+        }
+    }
+}
+
+// Expressions are evaluated by the service, against the balance it reads in its transaction.
+fn budget_amount(text: &str, config: &Config) -> (f64, Option<Expression>) {
+    match is_expression(text) {
+        true => (0.0, Some(Expression::new(text, &config.input_locale()))),
+        false => (parse_amount(text, config), None),
+    }
+}
+
+pub fn run_config(action: &ConfigAction) {
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Error: {}.", error);
+            process::exit(1);
+        }
+    };
+    match action {
+        ConfigAction::Get { key } => match config.get(key) {
+            Ok(value) => println!("{}", value),
+            Err(error) => print_error(&config, error),
+        },
+        ConfigAction::Set { key, value } => match config.set(key, value) {
+            Ok(()) => match config.save() {
+                Ok(path) => {
+                    print_success(&config, format!("{} updated in {}", key, path.display()))
+                }
+                Err(error) => print_error(&config, error),
+            },
+            Err(error) => print_error(&config, error),
+        },
+        ConfigAction::List => {
+            for (key, value) in config.list() {
+                println!("{} = {}", key, value);
+            }
+        }
+    }
+}
+
+pub fn print_completions(shell: &Shell) {
+    generate(*shell, &mut Cli::command(), "budget", &mut io::stdout());
+}
+
+pub fn run(mut db: Connection, command: Command, config: Config) {
+    let tz = config.tz();
+    let actor = &config.actor;
+    let snapshots = config.snapshots;
+
+    if let Err(error) = migrate(&db) {
+        print_error(&config, format!("{}.", error));
         process::exit(1);
     }
 
+    if let Err(error) = settle_pending_records(&db) {
+        print_error(&config, format!("{}.", error));
+        process::exit(1);
+    }
+
+    if snapshots > 0 && command.is_destructive() {
+        if let Err(error) = take_snapshot(&db, &snapshots) {
+            print_error(&config, format!("could not take snapshot: {}.", error));
+            process::exit(1);
+        }
+    }
+
     match &command {
+        Command::Audit { budget } => match get_audit_log(&db, budget) {
+            Ok(entries) => print_audit_entries(&entries, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Backup { path } => match backup_database(&db, path) {
+            Ok(path) => print_success(&config, format!("Database backed up to {}", path.display())),
+            Err(error) => print_error(&config, error),
+        },
         Command::Current {
             id,
             amount,
             description,
-        } => match set_current_funds(&db, id, amount, &command, description) {
-            Ok(rows) => println!("{} record updates", rows),
-            Err(error) => eprintln!("Error: {}", error),
+            date,
+        } => {
+            let (amount, expression) = budget_amount(amount, &config);
+            match set_current_funds(
+                &db,
+                id,
+                &amount,
+                &expression,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::History { id, limit, tree } => {
+            match get_history(&db, id, &limit.unwrap_or(config.history_limit), tree) {
+                Ok(records) => print_records(&records, &config),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Increase {
+            id,
+            amount,
+            description,
+            date,
+            account,
+            payee,
+        } => {
+            let (amount, expression) = budget_amount(amount, &config);
+            match increase_funds(
+                &db,
+                id,
+                &amount,
+                &expression,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+                account,
+                payee,
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Income {
+            amount,
+            description,
+            date,
+            account,
+        } => {
+            let amount = positive_amount(amount, &config);
+            match add_income(
+                &db,
+                &amount,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+                account,
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record inserted.", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Account {
+            action:
+                AccountAction::New {
+                    name,
+                    balance,
+                    kind,
+                    currency,
+                },
+        } => match create_account(
+            &db,
+            name,
+            kind,
+            &evaluate_amount(balance, &config),
+            &config.currency_code(currency),
+            &command,
+        ) {
+            Ok(rows) => print_success(&config, format!("{} record inserted.", rows)),
+            Err(error) => print_error(&config, error),
         },
-        Command::History { id } => match get_history(&db, id) {
-            Ok(records) => print_records(&records),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Account {
+            action: AccountAction::Remove { id },
+        } => match remove_account(&db, id, &command) {
+            Ok(rows) => print_success(&config, format!("{} record deleted.", rows)),
+            Err(error) => print_error(&config, error),
         },
-        Command::Increase {
+        Command::Accounts => match get_accounts(&db, &config.currency.code) {
+            Ok((accounts, allocations)) => print_accounts(&accounts, &allocations, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Assign {
             id,
             amount,
             description,
-        } => match increase_funds(&db, id, amount, &command, description) {
-            Ok(rows) => println!("{} record updates", rows),
-            Err(error) => eprintln!("Error: {}", error),
+            date,
+        } => match assign_income(
+            &db,
+            id,
+            &positive_amount(amount, &config),
+            &config.currency.code,
+            &command,
+            description,
+            &date.as_ref().map(|date| resolve_date(date, &tz)),
+        ) {
+            Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+            Err(error) => print_error(&config, error),
         },
         Command::Initial {
             id,
             amount,
             description,
-        } => match set_initial_funds(&db, id, amount, &command, description) {
-            Ok(rows) => println!("{} record updates", rows),
-            Err(error) => eprintln!("Error: {}", error),
+            date,
+        } => {
+            let (amount, expression) = budget_amount(amount, &config);
+            match set_initial_funds(
+                &db,
+                id,
+                &amount,
+                &expression,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::List {
+            as_of: None,
+            currency,
+            sort,
+            filter,
+            name,
+            forecast,
+        } => {
+            let budgets = get_budget_tree(&db, sort, filter, name, &config.warning_threshold);
+            match (budgets, currency) {
+                (Ok((budgets, depths)), None) => {
+                    let forecasts = forecast.then(|| {
+                        get_forecasts(
+                            &db,
+                            &budgets,
+                            &config.forecast_window,
+                            &config.forecast_weighting,
+                            &tz,
+                        )
+                    });
+                    match (get_trends(&db), forecasts.transpose(), get_unassigned(&db)) {
+                        (Ok(trends), Ok(forecasts), Ok(unassigned)) => print_budgets(
+                            &budgets,
+                            &depths,
+                            &trends,
+                            forecasts.as_ref(),
+                            unassigned,
+                            &config,
+                        ),
+                        (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+                            print_error(&config, error)
+                        }
+                    }
+                }
+                (Ok((budgets, depths)), Some(currency)) => {
+                    match get_conversion_rates(&db, &budgets, currency, &config.currency.code) {
+                        Ok(rates) => {
+                            print_converted_budgets(&budgets, &depths, &rates, currency, &config)
+                        }
+                        Err(error) => print_error(&config, error),
+                    }
+                }
+                (Err(error), _) => print_error(&config, error),
+            }
+        }
+        Command::List {
+            as_of: Some(as_of), ..
+        } => {
+            let instant = resolve_date(as_of, &tz);
+            match get_budgets_as_of(&db, &instant) {
+                Ok(states) => print_budget_states(&states, &instant, &config),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::New {
+            name,
+            funds,
+            currency,
+        } => match create_budget(
+            &db,
+            name,
+            &evaluate_amount(funds, &config),
+            &config.currency_code(currency),
+            &command,
+            actor,
+        ) {
+            Ok(rows) => print_success(&config, format!("{} record inserted.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Rates {
+            action: RatesAction::Import { path },
+        } => match import_exchange_rates(&db, path) {
+            Ok(rows) => print_success(&config, format!("{} exchange rates imported.", rows)),
+            Err(error) => print_error(&config, error),
         },
-        Command::List => match get_budgets(&db) {
-            Ok(budgets) => print_budgets(&budgets),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Rates {
+            action: RatesAction::List,
+        } => match get_exchange_rates(&db) {
+            Ok(rates) => print_rates(&rates, &config),
+            Err(error) => print_error(&config, error),
         },
-        Command::New { name, funds } => match create_budget(&db, name, funds) {
-            Ok(rows) => println!("{} record inserted.", rows),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Rates {
+            action: RatesAction::Set { from, to, rate },
+        } => match set_exchange_rate(&db, from, to, rate) {
+            Ok(rows) => print_success(&config, format!("{} exchange rate updated.", rows)),
+            Err(error) => print_error(&config, error),
         },
+        Command::Reconcile {
+            id,
+            account,
+            statement_balance,
+            as_of,
+            clear,
+            unclear,
+            finish,
+        } => {
+            let as_of = as_of
+                .as_ref()
+                .map(|as_of| resolve_date(as_of, &tz))
+                .unwrap_or(Utc::now());
+            let statement_balance = parse_amount(statement_balance, &config);
+            let reconciliation = match account {
+                Some(account) => reconcile_account(
+                    &db,
+                    account,
+                    &statement_balance,
+                    &as_of,
+                    clear,
+                    unclear,
+                    finish,
+                    &command,
+                ),
+                None => reconcile_budget(
+                    &db,
+                    &id.unwrap_or_default(),
+                    &statement_balance,
+                    &as_of,
+                    clear,
+                    unclear,
+                    finish,
+                    &command,
+                ),
+            };
+            match reconciliation {
+                Ok(reconciliation) => {
+                    print_reconciliation(&reconciliation, &config);
+                    if *finish {
+                        print_success(
+                            &config,
+                            String::from("Cleared records reconciled and locked"),
+                        );
+                    }
+                }
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Redo { count } => match redo(&db, count, &command, actor) {
+            Ok(operations) => print_operations("redo", &operations),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Reduce {
+            id: None,
+            amount: None,
+            description,
+            date,
+            split,
+            account,
+            payee,
+        } => {
+            if split.len() < 2 {
+                print_error(&config, "a split needs at least two parts.");
+                process::exit(1);
+            }
+            let parts: Vec<(u32, f64, Option<Expression>)> = split
+                .iter()
+                .map(|(id, amount)| {
+                    let (amount, expression) = budget_amount(amount, &config);
+                    (*id, amount, expression)
+                })
+                .collect();
+            match reduce_split(
+                &db,
+                &parts,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+                account,
+                payee,
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
         Command::Reduce {
             id,
             amount,
             description,
-        } => match reduce_funds(&db, id, amount, &command, description) {
-            Ok(rows) => println!("{} record updates", rows),
-            Err(error) => eprintln!("Error: {}", error),
+            date,
+            split: _,
+            account,
+            payee,
+        } => {
+            let (Some(id), Some(amount)) = (id, amount) else {
+                return;
+            };
+            let (amount, expression) = budget_amount(amount, &config);
+            match reduce_funds(
+                &db,
+                id,
+                &amount,
+                &expression,
+                &command,
+                description,
+                &date.as_ref().map(|date| resolve_date(date, &tz)),
+                account,
+                payee,
+            ) {
+                Ok(rows) => print_success(&config, format!("{} record updates", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Remove { id } => match remove_budget(&db, id, &command, actor) {
+            Ok(rows) => print_success(&config, format!("{} record deleted.", rows)),
+            Err(error) => print_error(&config, error),
         },
-        Command::Remove { id } => match remove_budget(&db, id) {
-            Ok(rows) => println!("{} record deleted.", rows),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Restore { path } => match restore_database(&mut db, path) {
+            Ok(()) => print_success(
+                &config,
+                format!("Database restored from {}", path.display()),
+            ),
+            Err(error) => print_error(&config, error),
         },
-        Command::Rename { id, name } => match rename_budget(&db, id, name) {
-            Ok(rows) => println!("{} record updated.", rows),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Rename { id, name } => match rename_budget(&db, id, name, &command, actor) {
+            Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Reset {
+            id,
+            description,
+            date,
+        } => match reset_funds(
+            &db,
+            id,
+            &command,
+            description,
+            &date.as_ref().map(|date| resolve_date(date, &tz)),
+        ) {
+            Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+            Err(error) => print_error(&config, error),
         },
-        Command::Reset { id, description } => match reset_funds(&db, id, &command, description) {
-            Ok(rows) => println!("{} record updated.", rows),
-            Err(error) => eprintln!("Error: {}", error),
+        Command::Chart {
+            view: ChartView::Spending { id, by, periods },
+        } => match get_spending(&db, id, by, periods, &tz) {
+            Ok((budget, totals)) => print_spending_chart(&budget, &totals, by, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Chart {
+            view: ChartView::Breakdown,
+        } => match get_budget_list(&db, &None, &None, &None, &config.warning_threshold) {
+            Ok(budgets) => print_breakdown_chart(&budgets, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Cap { id, amount } => {
+            let cap = amount
+                .as_ref()
+                .map(|amount| positive_amount(amount, &config));
+            match set_spending_cap(&db, id, &cap, &command, actor) {
+                Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Completions { shell } => print_completions(shell),
+        Command::Config { action } => run_config(action),
+        Command::Goal {
+            id,
+            target,
+            deadline,
+            clear: _,
+        } => {
+            let target = match (target, deadline) {
+                (Some(target), Some(deadline)) => {
+                    let amount = evaluate_amount(target, &config);
+                    if amount <= 0.0 {
+                        print_error(&config, "goal target must be greater than zero.");
+                        process::exit(1);
+                    }
+                    Some((amount, *deadline))
+                }
+                _ => None,
+            };
+            match set_goal(&db, id, &target, &tz, &command, actor) {
+                Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Goals => match get_goals(&db, &tz) {
+            Ok((budgets, progress)) => print_goals(&budgets, &progress, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Forecast {
+            id,
+            window,
+            weighting,
+        } => {
+            let budgets = match id {
+                Some(id) => get_budget(&db, id).map(|budget| vec![budget]),
+                None => get_budget_list(&db, &None, &None, &None, &config.warning_threshold),
+            };
+            let window = window.unwrap_or(config.forecast_window);
+            let weighting = weighting.unwrap_or(config.forecast_weighting);
+            match budgets.and_then(|budgets| {
+                Ok((
+                    get_forecasts(&db, &budgets, &window, &weighting, &tz)?,
+                    budgets,
+                ))
+            }) {
+                Ok((forecasts, budgets)) => print_forecasts(&budgets, &forecasts, &config),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Report {
+            kind: ReportKind::Variance { period, compare },
+        } => match get_variance_report(&db, period, compare, &tz) {
+            Ok((current, baseline, rows)) => {
+                print_variance_report(&rows, &current, &baseline, &config)
+            }
+            Err(error) => print_error(&config, error),
+        },
+        Command::Report {
+            kind: ReportKind::Monthly { month, top },
+        } => {
+            let month = month.unwrap_or_else(|| current_month(&tz));
+            match get_monthly_report(&db, &month, &tz, top) {
+                Ok((rows, descriptions)) => print_monthly_report(
+                    &rows,
+                    &descriptions,
+                    &month.format("%Y-%m").to_string(),
+                    &config,
+                ),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Report {
+            kind: ReportKind::Payees { from, to },
+        } => {
+            let (from, to) = match (from, to) {
+                (Some(from), Some(to)) => (*from, *to),
+                _ => {
+                    let month = current_month(&tz);
+                    (month, today(&tz))
+                }
+            };
+            match get_payee_report(&db, &from, &to, &tz) {
+                Ok(totals) => print_payee_report(
+                    &totals,
+                    &format!("{} to {}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d")),
+                    &config,
+                ),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Payee {
+            action: PayeeAction::List { prefix },
+        } => match get_payees(&db, prefix) {
+            Ok(payees) => print_payees(&payees, &config),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Payee {
+            action: PayeeAction::Alias { id, alias },
+        } => match add_payee_alias(&db, id, alias, &command, actor) {
+            Ok(rows) => print_success(&config, format!("{} record inserted.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Parent { id, parent } => {
+            match set_parent(&db, id, parent, &config.currency.code, &command, actor) {
+                Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Threshold { id, percent } => {
+            match set_warning_threshold(&db, id, percent, &command, actor) {
+                Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+                Err(error) => print_error(&config, error),
+            }
+        }
+        Command::Split {
+            action:
+                SplitAction::Edit {
+                    id,
+                    description,
+                    date,
+                },
+        } => match edit_split(
+            &db,
+            id,
+            description,
+            &date.as_ref().map(|date| resolve_date(date, &tz)),
+            &command,
+        ) {
+            Ok(rows) => print_success(&config, format!("{} record updated.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Split {
+            action: SplitAction::Delete { id },
+        } => match delete_split(&db, id, &command) {
+            Ok(rows) => print_success(&config, format!("{} records deleted.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Transfer {
+            from,
+            to,
+            amount,
+            rate,
+            description,
+            date,
+        } => match transfer_funds(
+            &db,
+            from,
+            to,
+            &positive_amount(amount, &config),
+            rate,
+            &config.currency.code,
+            &command,
+            description,
+            &date.as_ref().map(|date| resolve_date(date, &tz)),
+        ) {
+            Ok(rows) => print_success(&config, format!("{} records inserted.", rows)),
+            Err(error) => print_error(&config, error),
+        },
+        Command::Undo { count } => match undo(&db, count, &command, actor) {
+            Ok(operations) => print_operations("undo", &operations),
+            Err(error) => print_error(&config, error),
         },
     }
 }
+
+fn print_error(config: &Config, message: impl Display) {
+    let text = format!("Error: {}", message);
+    eprintln!("{}", paint(&text, config.error_style()));
+}
+
+fn print_success(config: &Config, message: String) {
+    println!("{}", paint(&message, config.style(Role::Success)));
+}
@@ -0,0 +1,164 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const COLORS: [(&str, &str); 20] = [
+    ("none", ""),
+    ("bold", "1"),
+    ("dim", "2"),
+    ("underline", "4"),
+    ("black", "30"),
+    ("red", "31"),
+    ("green", "32"),
+    ("yellow", "33"),
+    ("blue", "34"),
+    ("magenta", "35"),
+    ("cyan", "36"),
+    ("white", "37"),
+    ("bright_black", "90"),
+    ("bright_red", "91"),
+    ("bright_green", "92"),
+    ("bright_yellow", "93"),
+    ("bright_blue", "94"),
+    ("bright_magenta", "95"),
+    ("bright_cyan", "96"),
+    ("bright_white", "97"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                is_terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Negative,
+    Positive,
+    Warning,
+    Error,
+    Success,
+    Header,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub negative: String,
+    pub positive: String,
+    pub warning: String,
+    pub error: String,
+    pub success: String,
+    pub header: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            negative: String::from("red"),
+            positive: String::from("green"),
+            warning: String::from("yellow"),
+            error: String::from("bright_red"),
+            success: String::from("green"),
+            header: String::from("bold"),
+        }
+    }
+}
+
+impl Theme {
+    pub fn color(&self, role: Role) -> &str {
+        match role {
+            Role::Negative => &self.negative,
+            Role::Positive => &self.positive,
+            Role::Warning => &self.warning,
+            Role::Error => &self.error,
+            Role::Success => &self.success,
+            Role::Header => &self.header,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        [
+            Role::Negative,
+            Role::Positive,
+            Role::Warning,
+            Role::Error,
+            Role::Success,
+            Role::Header,
+        ]
+        .iter()
+        .all(|role| ansi_code(self.color(*role)).is_some())
+    }
+}
+
+pub fn ansi_code(name: &str) -> Option<&'static str> {
+    COLORS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        .map(|(_, code)| *code)
+}
+
+pub fn color_names() -> Vec<&'static str> {
+    COLORS.iter().map(|(name, _)| *name).collect()
+}
+
+pub fn paint(text: &str, code: Option<&str>) -> String {
+    match code {
+        Some(code) if !code.is_empty() && !text.is_empty() => {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paint_ok() {
+        assert_eq!(paint("-$5", ansi_code("red")), "\x1b[31m-$5\x1b[0m");
+        assert_eq!(paint("-$5", ansi_code("none")), "-$5");
+        assert_eq!(paint("-$5", None), "-$5");
+    }
+
+    #[test]
+    fn color_choice_ok() {
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn theme_ko() {
+        let theme = Theme {
+            warning: String::from("orange"),
+            ..Theme::default()
+        };
+        assert!(!theme.is_valid());
+        assert!(Theme::default().is_valid());
+    }
+}
@@ -0,0 +1,575 @@
+use crate::color::{ansi_code, color_names, ColorChoice, Role, Theme};
+use crate::currency::{find_currency, parse_currency_code};
+use crate::error::Error;
+use crate::forecast::Weighting;
+use crate::money::{find_locale, locale_names, parse_amount, Locale, MoneyFormat};
+use crate::output::OutputFormat;
+use chrono::format::{Item, StrftimeItems};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+pub const KEYS: [&str; 23] = [
+    "database",
+    "locale",
+    "date_format",
+    "history_limit",
+    "output",
+    "timezone",
+    "actor",
+    "snapshots",
+    "warning_threshold",
+    "forecast_window",
+    "forecast_weighting",
+    "color",
+    "theme.negative",
+    "theme.positive",
+    "theme.warning",
+    "theme.error",
+    "theme.success",
+    "theme.header",
+    "currency.code",
+    "currency.symbol",
+    "currency.precision",
+    "currency.thousands_separator",
+    "currency.decimal_separator",
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: Option<PathBuf>,
+    pub locale: Option<String>,
+    pub date_format: String,
+    pub history_limit: u32,
+    pub output: OutputFormat,
+    pub timezone: Option<String>,
+    pub actor: Option<String>,
+    pub snapshots: usize,
+    pub warning_threshold: f64,
+    pub forecast_window: u32,
+    pub forecast_weighting: Weighting,
+    pub color: ColorChoice,
+    pub theme: Theme,
+    pub currency: Currency,
+    pub currencies: BTreeMap<String, CurrencyFormat>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Currency {
+    pub code: String,
+    pub symbol: String,
+    pub precision: usize,
+    pub thousands_separator: Option<String>,
+    pub decimal_separator: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CurrencyFormat {
+    pub symbol: Option<String>,
+    pub precision: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database: None,
+            locale: None,
+            date_format: String::from("%d-%m-%Y"),
+            history_limit: 30,
+            output: OutputFormat::Table,
+            timezone: None,
+            actor: None,
+            snapshots: 0,
+            warning_threshold: 80.0,
+            forecast_window: 30,
+            forecast_weighting: Weighting::Flat,
+            color: ColorChoice::Auto,
+            theme: Theme::default(),
+            currency: Currency::default(),
+            currencies: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency {
+            code: String::from("USD"),
+            symbol: String::from("$"),
+            precision: 2,
+            thousands_separator: None,
+            decimal_separator: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, Error> {
+        match get_config_path() {
+            Some(path) if path.exists() => Config::parse(&fs::read_to_string(path)?),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, Error> {
+        let config: Config = toml::from_str(text)
+            .map_err(|error| Error::Invalid(format!("invalid configuration: {}", error)))?;
+        for key in KEYS {
+            config.validate(key)?;
+        }
+        config.validate("currencies")?;
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<PathBuf, Error> {
+        let path = get_config_path().ok_or(Error::Invalid(String::from(
+            "could not locate the configuration directory",
+        )))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string(self)
+            .map_err(|error| Error::Invalid(format!("could not write configuration: {}", error)))?;
+        fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    pub fn get(&self, key: &str) -> Result<String, Error> {
+        let value = match key {
+            "database" => self
+                .database
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            "locale" => self.locale.clone().unwrap_or_default(),
+            "date_format" => self.date_format.to_owned(),
+            "history_limit" => self.history_limit.to_string(),
+            "output" => self.output.value().to_string(),
+            "timezone" => self.timezone.clone().unwrap_or_default(),
+            "actor" => self.actor.clone().unwrap_or_default(),
+            "snapshots" => self.snapshots.to_string(),
+            "warning_threshold" => self.warning_threshold.to_string(),
+            "forecast_window" => self.forecast_window.to_string(),
+            "forecast_weighting" => self.forecast_weighting.value().to_string(),
+            "color" => self.color.value().to_string(),
+            "theme.negative" => self.theme.negative.to_owned(),
+            "theme.positive" => self.theme.positive.to_owned(),
+            "theme.warning" => self.theme.warning.to_owned(),
+            "theme.error" => self.theme.error.to_owned(),
+            "theme.success" => self.theme.success.to_owned(),
+            "theme.header" => self.theme.header.to_owned(),
+            "currency.code" => self.currency.code.to_owned(),
+            "currency.symbol" => self.currency.symbol.to_owned(),
+            "currency.precision" => self.currency.precision.to_string(),
+            "currency.thousands_separator" => self
+                .currency
+                .thousands_separator
+                .clone()
+                .unwrap_or_default(),
+            "currency.decimal_separator" => {
+                self.currency.decimal_separator.clone().unwrap_or_default()
+            }
+            _ => match split_currency_key(key) {
+                Some((code, "symbol")) => self
+                    .currencies
+                    .get(&code)
+                    .and_then(|format| format.symbol.clone())
+                    .unwrap_or_default(),
+                Some((code, "precision")) => self
+                    .currencies
+                    .get(&code)
+                    .and_then(|format| format.precision)
+                    .map(|precision| precision.to_string())
+                    .unwrap_or_default(),
+                _ => return Err(unknown_key(key)),
+            },
+        };
+        Ok(value)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let optional = |value: &str| match value {
+            "" => None,
+            value => Some(value.to_string()),
+        };
+        let invalid = || Error::Invalid(format!("invalid value '{}' for {}", value, key));
+        let mut config = self.clone();
+        match key {
+            "database" => config.database = optional(value).map(PathBuf::from),
+            "locale" => config.locale = optional(value),
+            "date_format" => config.date_format = value.to_string(),
+            "history_limit" => config.history_limit = value.parse().map_err(|_| invalid())?,
+            "output" => {
+                config.output = OutputFormat::from_str(value, true).map_err(|_| invalid())?
+            }
+            "timezone" => config.timezone = optional(value),
+            "actor" => config.actor = optional(value),
+            "snapshots" => config.snapshots = value.parse().map_err(|_| invalid())?,
+            "warning_threshold" => {
+                config.warning_threshold = value.parse().map_err(|_| invalid())?
+            }
+            "forecast_window" => config.forecast_window = value.parse().map_err(|_| invalid())?,
+            "forecast_weighting" => {
+                config.forecast_weighting =
+                    Weighting::from_str(value, true).map_err(|_| invalid())?
+            }
+            "color" => config.color = ColorChoice::from_str(value, true).map_err(|_| invalid())?,
+            "theme.negative" => config.theme.negative = value.to_string(),
+            "theme.positive" => config.theme.positive = value.to_string(),
+            "theme.warning" => config.theme.warning = value.to_string(),
+            "theme.error" => config.theme.error = value.to_string(),
+            "theme.success" => config.theme.success = value.to_string(),
+            "theme.header" => config.theme.header = value.to_string(),
+            "currency.code" => {
+                config.currency.code = parse_currency_code(value).map_err(|_| invalid())?
+            }
+            "currency.symbol" => config.currency.symbol = value.to_string(),
+            "currency.precision" => {
+                config.currency.precision = value.parse().map_err(|_| invalid())?
+            }
+            "currency.thousands_separator" => config.currency.thousands_separator = optional(value),
+            "currency.decimal_separator" => config.currency.decimal_separator = optional(value),
+            _ => {
+                let (code, field) = split_currency_key(key).ok_or(unknown_key(key))?;
+                let format = config.currencies.entry(code.to_owned()).or_default();
+                match field {
+                    "symbol" => format.symbol = optional(value),
+                    "precision" if value.is_empty() => format.precision = None,
+                    "precision" => format.precision = Some(value.parse().map_err(|_| invalid())?),
+                    _ => return Err(unknown_key(key)),
+                }
+                if format == &CurrencyFormat::default() {
+                    config.currencies.remove(&code);
+                }
+            }
+        }
+        config.validate(key)?;
+        config.validate("currencies")?;
+        *self = config;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = KEYS
+            .iter()
+            .map(|key| (key.to_string(), self.get(key).unwrap_or_default()))
+            .collect();
+        for (code, format) in &self.currencies {
+            if let Some(symbol) = &format.symbol {
+                entries.push((format!("currencies.{}.symbol", code), symbol.to_owned()));
+            }
+            if let Some(precision) = format.precision {
+                entries.push((
+                    format!("currencies.{}.precision", code),
+                    precision.to_string(),
+                ));
+            }
+        }
+        entries
+    }
+
+    pub fn currency_code(&self, currency: &Option<String>) -> String {
+        currency
+            .clone()
+            .unwrap_or_else(|| self.currency.code.to_owned())
+    }
+
+    pub fn number_locale(&self) -> Locale {
+        let mut locale = self
+            .locale
+            .as_ref()
+            .and_then(|locale| find_locale(locale))
+            .unwrap_or_default();
+        if let Some(separator) = &self.currency.thousands_separator {
+            locale.thousands_separator = separator.to_owned();
+        }
+        if let Some(separator) = &self.currency.decimal_separator {
+            locale.decimal_separator = separator.to_owned();
+        }
+        locale
+    }
+
+    pub fn money_format(&self, currency: &Option<String>) -> MoneyFormat {
+        let code = self.currency_code(currency);
+        if code == self.currency.code {
+            return MoneyFormat {
+                symbol: self.currency.symbol.to_owned(),
+                precision: self.currency.precision,
+                locale: self.number_locale(),
+            };
+        }
+        let known = find_currency(&code);
+        let format = self.currencies.get(&code);
+        let symbol = match (format.and_then(|format| format.symbol.clone()), known) {
+            (Some(symbol), _) => symbol,
+            (None, Some((symbol, _))) => symbol.to_string(),
+            (None, None) => format!("{} ", code),
+        };
+        let precision = format
+            .and_then(|format| format.precision)
+            .or(known.map(|(_, precision)| precision))
+            .unwrap_or(2);
+        MoneyFormat {
+            symbol,
+            precision,
+            locale: self.number_locale(),
+        }
+    }
+
+    pub fn input_locale(&self) -> Locale {
+        let unset = self.locale.is_none()
+            && self.currency.thousands_separator.is_none()
+            && self.currency.decimal_separator.is_none();
+        match unset {
+            true => Locale {
+                thousands_separator: String::from(","),
+                decimal_separator: String::from("."),
+                ..self.number_locale()
+            },
+            false => self.number_locale(),
+        }
+    }
+
+    pub fn parse_amount(&self, text: &str) -> Result<f64, Error> {
+        parse_amount(text, &self.input_locale()).map_err(Error::Invalid)
+    }
+
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone
+            .as_ref()
+            .and_then(|timezone| timezone.parse().ok())
+    }
+
+    pub fn style(&self, role: Role) -> Option<&'static str> {
+        if self.color.enabled(io::stdout().is_terminal()) {
+            ansi_code(self.theme.color(role))
+        } else {
+            None
+        }
+    }
+
+    pub fn error_style(&self) -> Option<&'static str> {
+        if self.color.enabled(io::stderr().is_terminal()) {
+            ansi_code(self.theme.color(Role::Error))
+        } else {
+            None
+        }
+    }
+
+    pub fn amount_style(&self, amount: f64) -> Option<&'static str> {
+        if amount < 0.0 {
+            self.style(Role::Negative)
+        } else {
+            None
+        }
+    }
+
+    pub fn datetime_format(&self) -> String {
+        format!("{} %H:%M", self.date_format)
+    }
+
+    fn validate(&self, key: &str) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::Invalid(format!("{} {}", key, reason)));
+        match key {
+            "currency.code" if parse_currency_code(&self.currency.code).is_err() => {
+                invalid("must be a three letter currency code")
+            }
+            "currencies"
+                if self.currencies.iter().any(|(code, format)| {
+                    parse_currency_code(code).as_ref() != Ok(code)
+                        || format.precision.is_some_and(|precision| precision > 10)
+                }) =>
+            {
+                invalid("must use three letter codes and a precision between 0 and 10")
+            }
+            "date_format"
+                if self.date_format.is_empty()
+                    || StrftimeItems::new(&self.date_format).any(|item| item == Item::Error) =>
+            {
+                invalid("is not a valid date format")
+            }
+            "history_limit" if self.history_limit == 0 => invalid("must be greater than zero"),
+            "forecast_window" if !is_valid_window(&self.forecast_window) => {
+                invalid("must be between 1 and 365 days")
+            }
+            "theme.negative" | "theme.positive" | "theme.warning" | "theme.error"
+            | "theme.success" | "theme.header"
+                if !self.theme.is_valid() =>
+            {
+                invalid(&format!("must be one of: {}", color_names().join(", ")))
+            }
+            "warning_threshold" if !is_valid_threshold(&self.warning_threshold) => {
+                invalid("must be a percentage between 0 and 100")
+            }
+            "timezone"
+                if self
+                    .timezone
+                    .as_ref()
+                    .is_some_and(|timezone| timezone.parse::<Tz>().is_err()) =>
+            {
+                invalid("is not a known timezone")
+            }
+            "currency.precision" if self.currency.precision > 10 => {
+                invalid("must be between 0 and 10")
+            }
+            "locale"
+                if self
+                    .locale
+                    .as_ref()
+                    .is_some_and(|locale| find_locale(locale).is_none()) =>
+            {
+                invalid(&format!("must be one of: {}", locale_names().join(", ")))
+            }
+            "currency.thousands_separator" | "currency.decimal_separator"
+                if self.number_locale().thousands_separator
+                    == self.number_locale().decimal_separator =>
+            {
+                invalid("must differ from the other separator")
+            }
+            "currency.decimal_separator"
+                if !matches!(self.number_locale().decimal_separator.as_str(), "." | ",") =>
+            {
+                invalid("must be '.' or ','")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn get_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("BUDGET_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = match env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => home::home_dir()?.join(".config"),
+    };
+    Some(config_dir.join("budget").join("config.toml"))
+}
+
+pub fn is_valid_threshold(threshold: &f64) -> bool {
+    (0.0..=100.0).contains(threshold)
+}
+
+pub fn is_valid_window(window: &u32) -> bool {
+    (1..=365).contains(window)
+}
+
+fn split_currency_key(key: &str) -> Option<(String, &str)> {
+    let rest = key.strip_prefix("currencies.")?;
+    let (code, field) = rest.split_once('.')?;
+    Some((parse_currency_code(code).ok()?, field))
+}
+
+fn unknown_key(key: &str) -> Error {
+    Error::Invalid(format!(
+        "unknown configuration key '{}', expected one of: {} or currencies.<CODE>.symbol/precision",
+        key,
+        KEYS.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ok() {
+        let config = Config::parse(
+            "database = \"/tmp/budget.db3\"
+            history_limit = 10
+            output = \"csv\"
+
+            [currency]
+            symbol = \"€\"",
+        )
+        .unwrap();
+        assert_eq!(config.database, Some(PathBuf::from("/tmp/budget.db3")));
+        assert_eq!(config.history_limit, 10);
+        assert_eq!(config.output, OutputFormat::Csv);
+        assert_eq!(config.currency.symbol, "€");
+        assert_eq!(config.currency.precision, 2);
+        assert_eq!(config.date_format, "%d-%m-%Y");
+    }
+
+    #[test]
+    fn parse_ko() {
+        assert!(Config::parse("colour = \"red\"").is_err());
+        assert!(Config::parse("timezone = \"Mars/Olympus\"").is_err());
+    }
+
+    #[test]
+    fn set_ok() {
+        let mut config = Config::default();
+        config.set("currency.precision", "0").unwrap();
+        config.set("output", "json").unwrap();
+        config.set("actor", "ana").unwrap();
+        config.set("theme.negative", "magenta").unwrap();
+        assert_eq!(config.get("currency.precision").unwrap(), "0");
+        assert_eq!(config.output, OutputFormat::Json);
+        config.set("actor", "").unwrap();
+        assert_eq!(config.actor, None);
+        let text = toml::to_string(&config).unwrap();
+        assert_eq!(Config::parse(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn money_format_ok() {
+        let mut config = Config::default();
+        config.set("currencies.eur.symbol", "EUR ").unwrap();
+        assert_eq!(
+            config.money_format(&Some(String::from("EUR"))).format(5.0),
+            "EUR 5,00"
+        );
+        assert_eq!(
+            config
+                .money_format(&Some(String::from("JPY")))
+                .format(1500.0),
+            "¥1.500"
+        );
+        assert_eq!(config.money_format(&None).format(5.0), "$5,00");
+    }
+
+    #[test]
+    fn number_locale_ok() {
+        let mut config = Config::default();
+        config.set("locale", "en-US").unwrap();
+        assert_eq!(config.get("locale").unwrap(), "en-US");
+        assert_eq!(config.parse_amount("$1,200").unwrap(), 1200.0);
+        assert_eq!(config.money_format(&None).format(1200.0), "$1,200.00");
+        config.set("currency.thousands_separator", " ").unwrap();
+        assert_eq!(config.money_format(&None).format(1200.0), "$1 200.00");
+        assert!(config.set("currency.decimal_separator", " ").is_err());
+        assert!(config.set("locale", "xx-XX").is_err());
+    }
+
+    #[test]
+    fn input_locale_ok() {
+        let mut config = Config::default();
+        assert_eq!(config.parse_amount("1.500").unwrap(), 1.5);
+        assert_eq!(config.parse_amount("1,500.25").unwrap(), 1500.25);
+        config.set("locale", "es-ES").unwrap();
+        assert_eq!(config.parse_amount("1.500").unwrap(), 1500.0);
+    }
+
+    #[test]
+    fn set_ko() {
+        let mut config = Config::default();
+        assert!(config.set("history_limit", "none").is_err());
+        assert!(config.set("history_limit", "0").is_err());
+        assert!(config.set("date_format", "%Q").is_err());
+        assert!(config.set("colour", "red").is_err());
+        assert!(config.set("theme.warning", "orange").is_err());
+        assert!(config.set("color", "sometimes").is_err());
+        assert!(config.set("forecast_window", "0").is_err());
+        assert_eq!(config, Config::default());
+    }
+}
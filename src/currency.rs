@@ -0,0 +1,57 @@
+const KNOWN_CURRENCIES: [(&str, &str, usize); 14] = [
+    ("AUD", "A$", 2),
+    ("BRL", "R$", 2),
+    ("CAD", "C$", 2),
+    ("CHF", "CHF ", 2),
+    ("CNY", "¥", 2),
+    ("COP", "COL$", 0),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("INR", "₹", 2),
+    ("JPY", "¥", 0),
+    ("KRW", "₩", 0),
+    ("MXN", "MX$", 2),
+    ("SEK", "kr ", 2),
+    ("USD", "$", 2),
+];
+
+pub fn find_currency(code: &str) -> Option<(&'static str, usize)> {
+    KNOWN_CURRENCIES
+        .iter()
+        .find(|(known, _, _)| *known == code)
+        .map(|(_, symbol, precision)| (*symbol, *precision))
+}
+
+pub fn parse_currency_code(text: &str) -> Result<String, String> {
+    let code = text.trim().to_uppercase();
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(code)
+    } else {
+        Err(format!(
+            "invalid currency code '{}', expected three letters such as EUR",
+            text
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_currency_code_ok() {
+        assert_eq!(parse_currency_code("eur").unwrap(), "EUR");
+    }
+
+    #[test]
+    fn parse_currency_code_ko() {
+        assert!(parse_currency_code("EURO").is_err());
+        assert!(parse_currency_code("E1R").is_err());
+    }
+
+    #[test]
+    fn find_currency_ok() {
+        assert_eq!(find_currency("JPY"), Some(("¥", 0)));
+        assert!(find_currency("XXX").is_none());
+    }
+}
@@ -1,6 +1,50 @@
+use crate::account::create_account_tables;
+use crate::audit::{allow_unscoped_audit, create_audit_table};
+use crate::budget::{add_currency_column, add_threshold_column, create_budget_table};
+use crate::dates::normalise_timestamps;
+use crate::goal::add_goal_columns;
+use crate::hierarchy::add_hierarchy_columns;
+use crate::income::create_income_table;
+use crate::journal::create_journal_tables;
+use crate::payee::create_payee_tables;
+use crate::rates::create_rates_table;
+use crate::reconcile::add_status_column;
+use crate::record::{
+    add_balance_columns, add_expression_column, add_pending_column, add_rate_column,
+    create_record_table,
+};
+use crate::split::create_split_tables;
+use crate::timeline::create_timeline_indexes;
 use rusqlite::Connection;
 use std::{path::PathBuf, process};
 
+type Migration = fn(&Connection) -> Result<(), rusqlite::Error>;
+
+const MIGRATIONS: &[Migration] = &[
+    create_base_schema,
+    create_journal_tables,
+    create_audit_table,
+    add_balance_columns,
+    create_timeline_indexes,
+    add_pending_column,
+    normalise_timestamps,
+    add_currency_column,
+    add_rate_column,
+    create_rates_table,
+    add_expression_column,
+    add_threshold_column,
+    add_goal_columns,
+    create_income_table,
+    create_split_tables,
+    add_hierarchy_columns,
+    create_account_tables,
+    add_status_column,
+    create_payee_tables,
+    allow_unscoped_audit,
+];
+
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
 pub fn open_db(path: Option<PathBuf>) -> Connection {
     let path = match path {
         Some(path) => path,
@@ -29,3 +73,48 @@ fn get_default_db() -> Option<PathBuf> {
         home
     })
 }
+
+pub fn get_schema_version(db: &Connection) -> Result<i32, rusqlite::Error> {
+    db.query_row("PRAGMA user_version;", [], |row| row.get(0))
+}
+
+fn set_schema_version(db: &Connection, version: i32) -> Result<(), rusqlite::Error> {
+    db.pragma_update(None, "user_version", version)
+}
+
+pub fn migrate(db: &Connection) -> Result<(), rusqlite::Error> {
+    let version = get_schema_version(db)?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let tx = db.unchecked_transaction()?;
+        migration(&tx)?;
+        set_schema_version(&tx, index as i32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn create_base_schema(db: &Connection) -> Result<(), rusqlite::Error> {
+    create_budget_table(db)?;
+    create_record_table(db)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        assert_eq!(get_schema_version(&db).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migrate_twice_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        migrate(&db).unwrap();
+        assert_eq!(get_schema_version(&db).unwrap(), SCHEMA_VERSION);
+    }
+}
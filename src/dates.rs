@@ -0,0 +1,280 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use rusqlite::Connection;
+
+#[derive(Clone, Debug)]
+pub enum DateInput {
+    Instant(DateTime<Utc>),
+    Wall(NaiveDateTime),
+}
+
+impl DateInput {
+    pub fn resolve(&self, tz: &Option<Tz>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Instant(instant) => Some(*instant),
+            Self::Wall(naive) => to_utc(naive, tz),
+        }
+    }
+}
+
+pub fn to_utc(naive: &NaiveDateTime, tz: &Option<Tz>) -> Option<DateTime<Utc>> {
+    match tz {
+        Some(tz) => tz
+            .from_local_datetime(naive)
+            .earliest()
+            .map(|date| date.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(naive)
+            .earliest()
+            .map(|date| date.with_timezone(&Utc)),
+    }
+}
+
+pub fn format_datetime(date: &DateTime<Utc>, tz: &Option<Tz>, format: &str) -> String {
+    match tz {
+        Some(tz) => date.with_timezone(tz).format(format).to_string(),
+        None => date.with_timezone(&Local).format(format).to_string(),
+    }
+}
+
+pub fn parse_timezone(text: &str) -> Result<Tz, String> {
+    text.parse::<Tz>()
+        .map_err(|_| format!("unknown timezone '{}'", text))
+}
+
+fn parse_wall(text: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+}
+
+pub fn parse_as_of(text: &str) -> Result<DateInput, String> {
+    let naive = match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_nano_opt(23, 59, 59, 999_999_999),
+        Err(_) => parse_wall(text),
+    };
+    naive.map(DateInput::Wall).ok_or(format!(
+        "invalid date '{}', expected YYYY-MM-DD or YYYY-MM-DDTHH:MM",
+        text
+    ))
+}
+
+pub fn parse_date(text: &str) -> Result<DateInput, String> {
+    let now = Utc::now();
+    let error = || {
+        format!(
+            "invalid date '{}', expected YYYY-MM-DD[THH:MM], today, yesterday, tomorrow or an offset such as -3d",
+            text
+        )
+    };
+    let one_day = Duration::try_days(1).ok_or_else(error)?;
+    match text.to_lowercase().as_str() {
+        "now" | "today" => return Ok(DateInput::Instant(now)),
+        "yesterday" => return Ok(DateInput::Instant(now - one_day)),
+        "tomorrow" => return Ok(DateInput::Instant(now + one_day)),
+        _ => (),
+    }
+    if let Some(sign) = text.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let unit = text.chars().last().ok_or_else(error)?;
+        let digits = text
+            .get(sign.len_utf8()..text.len() - unit.len_utf8())
+            .ok_or_else(error)?;
+        let count: u32 = digits.parse().map_err(|_| error())?;
+        let date = match (sign, unit.to_ascii_lowercase()) {
+            ('+', 'd') => Duration::try_days(count.into()).and_then(|d| now.checked_add_signed(d)),
+            ('-', 'd') => Duration::try_days(count.into()).and_then(|d| now.checked_sub_signed(d)),
+            ('+', 'w') => Duration::try_weeks(count.into()).and_then(|d| now.checked_add_signed(d)),
+            ('-', 'w') => Duration::try_weeks(count.into()).and_then(|d| now.checked_sub_signed(d)),
+            ('+', 'm') => now.checked_add_months(Months::new(count)),
+            ('-', 'm') => now.checked_sub_months(Months::new(count)),
+            _ => None,
+        };
+        return date.map(DateInput::Instant).ok_or_else(error);
+    }
+    let naive = match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0),
+        Err(_) => parse_wall(text),
+    };
+    naive.map(DateInput::Wall).ok_or_else(error)
+}
+
+pub fn parse_month(text: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", text), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month '{}', expected YYYY-MM", text))
+}
+
+pub fn parse_day(text: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", text))
+}
+
+pub fn parse_deadline(text: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .or_else(|| {
+            let month = parse_month(text).ok()?;
+            month.checked_add_months(Months::new(1))?.pred_opt()
+        })
+        .ok_or(format!(
+            "invalid date '{}', expected YYYY-MM-DD or YYYY-MM for the end of that month",
+            text
+        ))
+}
+
+pub fn today(tz: &Option<Tz>) -> NaiveDate {
+    match tz {
+        Some(tz) => Utc::now().with_timezone(tz).date_naive(),
+        None => Local::now().date_naive(),
+    }
+}
+
+pub fn current_month(tz: &Option<Tz>) -> NaiveDate {
+    today(tz).with_day(1).unwrap()
+}
+
+pub fn date_range(
+    start: &NaiveDate,
+    end: &NaiveDate,
+    tz: &Option<Tz>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = to_utc(&start.and_hms_opt(0, 0, 0)?, tz)?;
+    let end = to_utc(&end.and_hms_opt(0, 0, 0)?, tz)?;
+    Some((start, end))
+}
+
+pub fn month_range(month: &NaiveDate, tz: &Option<Tz>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    date_range(month, &month.checked_add_months(Months::new(1))?, tz)
+}
+
+pub fn normalise_timestamps(db: &Connection) -> Result<(), rusqlite::Error> {
+    for table in ["records", "audit", "operations"] {
+        let query = format!(
+            "SELECT rowid, created_at FROM {} WHERE created_at IS NOT NULL;",
+            table
+        );
+        let mut stmt = db.prepare(&query)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, DateTime<Utc>>(1).ok()))
+            })?
+            .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+        let query = format!("UPDATE {} SET created_at = ?1 WHERE rowid = ?2;", table);
+        for (row_id, created_at) in rows {
+            if let Some(created_at) = created_at {
+                db.execute(&query, (created_at, row_id))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn parse_as_of_date_ok() {
+        let tz = Some(Tz::UTC);
+        let instant = parse_as_of("2024-03-31").unwrap().resolve(&tz).unwrap();
+        assert_eq!(instant.format("%Y-%m-%d").to_string(), "2024-03-31");
+        assert_eq!(instant.hour(), 23);
+    }
+
+    #[test]
+    fn parse_as_of_time_ok() {
+        let tz = Some(Tz::UTC);
+        let instant = parse_as_of("2024-03-31T08:15")
+            .unwrap()
+            .resolve(&tz)
+            .unwrap();
+        assert_eq!(
+            instant.format("%Y-%m-%d %H:%M").to_string(),
+            "2024-03-31 08:15"
+        );
+    }
+
+    #[test]
+    fn parse_as_of_ko() {
+        assert!(parse_as_of("31/03/2024").is_err());
+    }
+
+    #[test]
+    fn parse_date_ok() {
+        let tz = Some(Tz::UTC);
+        let date = parse_date("2024-03-31").unwrap().resolve(&tz).unwrap();
+        assert_eq!(
+            date.format("%Y-%m-%d %H:%M").to_string(),
+            "2024-03-31 00:00"
+        );
+    }
+
+    #[test]
+    fn parse_date_relative_ok() {
+        let date = parse_date("-3d").unwrap().resolve(&None).unwrap();
+        let expected = (Utc::now() - Duration::try_days(3).unwrap()).date_naive();
+        assert_eq!(date.date_naive(), expected);
+    }
+
+    #[test]
+    fn parse_date_yesterday_ok() {
+        let date = parse_date("yesterday").unwrap().resolve(&None).unwrap();
+        let expected = (Utc::now() - Duration::try_days(1).unwrap()).date_naive();
+        assert_eq!(date.date_naive(), expected);
+    }
+
+    #[test]
+    fn month_range_ok() {
+        let month = parse_month("2024-03").unwrap();
+        let (start, end) = month_range(&month, &Some(Tz::Europe__Madrid)).unwrap();
+        assert_eq!(start.to_rfc3339(), "2024-02-29T23:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2024-03-31T22:00:00+00:00");
+        assert!(parse_month("2024-13").is_err());
+        assert_eq!(
+            parse_deadline("2024-02"),
+            Ok(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_date_ko() {
+        assert!(parse_date("-3x").is_err());
+        assert!(parse_date("last week").is_err());
+        assert!(parse_date("-").is_err());
+        assert!(parse_date("+d").is_err());
+    }
+
+    #[test]
+    fn parse_date_timezone_ok() {
+        let tz = Some(parse_timezone("Europe/Madrid").unwrap());
+        let date = parse_date("2024-07-01T10:00")
+            .unwrap()
+            .resolve(&tz)
+            .unwrap();
+        assert_eq!(date.format("%H:%M").to_string(), "08:00");
+    }
+
+    #[test]
+    fn parse_timezone_ko() {
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn normalise_timestamps_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE records (created_at TEXT);
+            CREATE TABLE audit (created_at TEXT);
+            CREATE TABLE operations (created_at TEXT);
+            INSERT INTO records VALUES ('2024-01-01 10:00:00+02:00');",
+        )
+        .unwrap();
+        normalise_timestamps(&db).unwrap();
+        let created_at: String = db
+            .query_row("SELECT created_at FROM records;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(created_at, "2024-01-01 08:00:00+00:00");
+    }
+}
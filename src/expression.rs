@@ -0,0 +1,256 @@
+use crate::money::{parse_amount, Locale};
+
+const MAX_LENGTH: usize = 256;
+const MAX_DEPTH: usize = 32;
+const OPERATORS: [char; 7] = ['+', '-', '*', '/', '%', '(', ')'];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Percent,
+    Open,
+    Close,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    balance: Option<f64>,
+    text: &'a str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub text: String,
+    pub locale: Locale,
+}
+
+impl Expression {
+    pub fn new(text: &str, locale: &Locale) -> Self {
+        Self {
+            text: text.trim().to_string(),
+            locale: locale.clone(),
+        }
+    }
+
+    pub fn evaluate(&self, balance: Option<f64>) -> Result<f64, String> {
+        evaluate(&self.text, &self.locale, balance)
+    }
+}
+
+pub fn is_expression(text: &str) -> bool {
+    let text = text.trim();
+    let accounting = text
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+        .is_some_and(|inner| !inner.contains(OPERATORS));
+    let body = text.trim_start_matches(['-', '+']);
+    !accounting && body.contains(OPERATORS)
+}
+
+pub fn evaluate(text: &str, locale: &Locale, balance: Option<f64>) -> Result<f64, String> {
+    if text.len() > MAX_LENGTH {
+        return Err(format!(
+            "expression is longer than {} characters",
+            MAX_LENGTH
+        ));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(text, locale)?,
+        position: 0,
+        depth: 0,
+        balance,
+        text,
+    };
+    let value = parser.expression()?;
+    if parser.position < parser.tokens.len() {
+        return Err(parser.error());
+    }
+    if !value.is_finite() {
+        return Err(format!(
+            "expression '{}' does not give a finite amount",
+            text
+        ));
+    }
+    Ok(value)
+}
+
+fn tokenize(text: &str, locale: &Locale) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut operand = String::new();
+    let flush = |operand: &mut String, tokens: &mut Vec<Token>| -> Result<(), String> {
+        if !operand.trim().is_empty() {
+            tokens.push(Token::Number(parse_amount(operand, locale)?));
+        }
+        operand.clear();
+        Ok(())
+    };
+    for c in text.chars() {
+        let token = match c {
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' | '×' => Token::Times,
+            '/' | '÷' => Token::Divide,
+            '%' => Token::Percent,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            c => {
+                operand.push(c);
+                continue;
+            }
+        };
+        flush(&mut operand, &mut tokens)?;
+        tokens.push(token);
+    }
+    flush(&mut operand, &mut tokens)?;
+    Ok(tokens)
+}
+
+impl Parser<'_> {
+    fn error(&self) -> String {
+        format!("invalid expression '{}'", self.text)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(token @ (Token::Plus | Token::Minus)) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.term()?;
+            value = match token {
+                Token::Plus => value + right,
+                _ => value - right,
+            };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(token @ (Token::Times | Token::Divide)) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.unary()?;
+            value = match token {
+                Token::Times => value * right,
+                _ if right == 0.0 => return Err(format!("division by zero in '{}'", self.text)),
+                _ => value / right,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.position += 1;
+                Ok(-self.nested(Self::unary)?)
+            }
+            Some(Token::Plus) => {
+                self.position += 1;
+                self.nested(Self::unary)
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<f64, String> {
+        let value = self.primary()?;
+        if self.peek() != Some(&Token::Percent) {
+            return Ok(value);
+        }
+        self.position += 1;
+        match self.balance {
+            Some(balance) => Ok(balance * value / 100.0),
+            None => Err(String::from(
+                "percentages need a current balance to apply to",
+            )),
+        }
+    }
+
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(value),
+            Some(Token::Open) => {
+                let value = self.nested(Self::expression)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(value),
+                    _ => Err(self.error()),
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+
+    fn nested(&mut self, rule: fn(&mut Self) -> Result<f64, String>) -> Result<f64, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("expression '{}' is nested too deeply", self.text));
+        }
+        let value = rule(self);
+        self.depth -= 1;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::find_locale;
+
+    fn locale() -> Locale {
+        find_locale("en-US").unwrap()
+    }
+
+    #[test]
+    fn evaluate_ok() {
+        let result = evaluate("12.99+4.50*2", &locale(), None).unwrap();
+        assert!((result - 21.99).abs() < 1e-9);
+        assert_eq!(evaluate("(10 + 5) / 3", &locale(), None), Ok(5.0));
+        assert_eq!(evaluate("-(2-5)", &locale(), None), Ok(3.0));
+        assert_eq!(evaluate("$1,200 - 200", &locale(), None), Ok(1000.0));
+        assert_eq!(evaluate("7", &locale(), None), Ok(7.0));
+    }
+
+    #[test]
+    fn evaluate_percent_ok() {
+        assert_eq!(evaluate("10%", &locale(), Some(250.0)), Ok(25.0));
+        assert_eq!(evaluate("50% + 1", &locale(), Some(10.0)), Ok(6.0));
+    }
+
+    #[test]
+    fn evaluate_ko() {
+        assert!(evaluate("10%", &locale(), None).is_err());
+        assert!(evaluate("4/0", &locale(), None).is_err());
+        assert!(evaluate("(1+2", &locale(), None).is_err());
+        assert!(evaluate("1+", &locale(), None).is_err());
+        assert!(evaluate("2 % 3", &locale(), Some(1.0)).is_err());
+        assert!(evaluate("abc*2", &locale(), None).is_err());
+        assert!(evaluate(&"(".repeat(100), &locale(), None).is_err());
+    }
+
+    #[test]
+    fn is_expression_ok() {
+        assert!(is_expression("12.99+4.50*2"));
+        assert!(is_expression("10%"));
+        assert!(!is_expression("-12.50"));
+        assert!(!is_expression("$1,200"));
+        assert!(!is_expression("(4)"));
+        assert!(!is_expression("($1,200.50)"));
+        assert!(is_expression("(4)+1"));
+        assert!(is_expression("(-4)"));
+    }
+}
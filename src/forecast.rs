@@ -0,0 +1,236 @@
+use crate::budget::Budget;
+use crate::color::Role;
+use crate::config::Config;
+use crate::dates::to_utc;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::reduction_actions;
+use crate::table::{Column, Table};
+use chrono::{DateTime, Datelike, Days, Local, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    #[default]
+    Flat,
+    Linear,
+    Exponential,
+}
+
+impl Weighting {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Flat => "flat",
+            Self::Linear => "linear",
+            Self::Exponential => "exponential",
+        }
+    }
+
+    fn weight(&self, age: u32, window: u32) -> f64 {
+        match self {
+            Self::Flat => 1.0,
+            Self::Linear => f64::from(window - age),
+            Self::Exponential => {
+                let half_life = (f64::from(window) / 4.0).max(1.0);
+                0.5_f64.powf(f64::from(age) / half_life)
+            }
+        }
+    }
+}
+
+pub struct Forecast {
+    pub burn_rate: f64,
+    pub exhaustion: Option<NaiveDate>,
+    pub period_end: NaiveDate,
+    pub end_balance: f64,
+    pub safe_per_day: f64,
+}
+
+pub fn get_daily_reductions(
+    db: &Connection,
+    today: &NaiveDate,
+    window: &u32,
+    tz: &Option<Tz>,
+) -> Result<HashMap<u32, Vec<f64>>, rusqlite::Error> {
+    let first_day = *today - Days::new(u64::from(*window) - 1);
+    let since = first_day
+        .and_hms_opt(0, 0, 0)
+        .and_then(|start| to_utc(&start, tz))
+        .unwrap_or_default();
+    let query = format!(
+        "
+        SELECT budget_id, created_at, amount
+        FROM records
+        WHERE action IN {} AND pending = 0 AND created_at >= ?1;",
+        reduction_actions()
+    );
+    let mut stmt = db.prepare(&query)?;
+    let rows = stmt.query_map([since], |row| {
+        Ok((
+            row.get::<_, u32>(0)?,
+            row.get::<_, DateTime<Utc>>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+    let mut daily: HashMap<u32, Vec<f64>> = HashMap::new();
+    for row in rows {
+        let (budget_id, created_at, amount) = row?;
+        let date = match tz {
+            Some(tz) => created_at.with_timezone(tz).date_naive(),
+            None => created_at.with_timezone(&Local).date_naive(),
+        };
+        let Ok(age) = usize::try_from((*today - date).num_days()) else {
+            continue;
+        };
+        let days = daily
+            .entry(budget_id)
+            .or_insert_with(|| vec![0.0; *window as usize]);
+        if let Some(total) = days.get_mut(age) {
+            *total += amount;
+        }
+    }
+    Ok(daily)
+}
+
+pub fn burn_rate(daily: &[f64], window: &u32, weighting: &Weighting) -> f64 {
+    let (weighted, weights) = (0..*window).fold((0.0, 0.0), |(weighted, weights), age| {
+        let weight = weighting.weight(age, *window);
+        let amount = daily.get(age as usize).copied().unwrap_or_default();
+        (weighted + weight * amount, weights + weight)
+    });
+    if weights > 0.0 {
+        weighted / weights
+    } else {
+        0.0
+    }
+}
+
+pub fn project(budget: &Budget, burn_rate: f64, today: &NaiveDate) -> Forecast {
+    let period_end = today.with_day(1).unwrap() + Months::new(1) - Days::new(1);
+    let days_left = (period_end - *today).num_days() as f64 + 1.0;
+    let funds = budget.current_funds;
+    let exhaustion = if funds <= 0.0 {
+        Some(*today)
+    } else if burn_rate > 0.0 {
+        today.checked_add_days(Days::new((funds / burn_rate).floor() as u64))
+    } else {
+        None
+    };
+    Forecast {
+        burn_rate,
+        exhaustion,
+        period_end,
+        end_balance: funds - burn_rate * days_left,
+        safe_per_day: funds.max(0.0) / days_left,
+    }
+}
+
+pub fn format_exhaustion(forecast: &Forecast, config: &Config) -> String {
+    match forecast.exhaustion {
+        Some(date) => date.format(&config.date_format).to_string(),
+        None => String::from("never"),
+    }
+}
+
+pub fn print_forecasts(budgets: &[Budget], forecasts: &HashMap<u32, Forecast>, config: &Config) {
+    let rows: Vec<(&Budget, &Forecast)> = budgets
+        .iter()
+        .filter_map(|budget| Some((budget, forecasts.get(&budget.budget_id?)?)))
+        .collect();
+    if config.output != OutputFormat::Table {
+        let cells: Vec<Vec<Cell>> = rows
+            .iter()
+            .map(|(budget, forecast)| {
+                vec![
+                    Cell::optional_number(budget.budget_id.map(f64::from)),
+                    Cell::text(&budget.name),
+                    Cell::Text(config.currency_code(&budget.currency)),
+                    Cell::Number(budget.current_funds),
+                    Cell::Number(forecast.burn_rate),
+                    Cell::optional_text(&forecast.exhaustion.map(|date| date.to_string())),
+                    Cell::Text(forecast.period_end.to_string()),
+                    Cell::Number(forecast.end_balance),
+                    Cell::Number(forecast.safe_per_day),
+                ]
+            })
+            .collect();
+        let headers = [
+            "id",
+            "budget",
+            "currency",
+            "current_funds",
+            "burn_rate",
+            "runs_out",
+            "period_end",
+            "end_balance",
+            "safe_per_day",
+        ];
+        return print_rows(&config.output, &headers, &cells);
+    }
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::right("CURRENT FUNDS"),
+        Column::right("PER DAY"),
+        Column::left("RUNS OUT").keep(),
+        Column::right("END OF MONTH"),
+        Column::right("SAFE PER DAY"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for (budget, forecast) in rows {
+        let money = config.money_format(&budget.currency);
+        table.add_row(vec![
+            budget.budget_id.unwrap().to_string(),
+            budget.name.to_owned(),
+            money.format(budget.current_funds),
+            money.format(forecast.burn_rate),
+            format_exhaustion(forecast, config),
+            money.format(forecast.end_balance),
+            money.format(forecast.safe_per_day),
+        ]);
+        let runs_out_early = forecast
+            .exhaustion
+            .is_some_and(|date| date < forecast.period_end);
+        if runs_out_early {
+            table.style_cell(4, config.style(Role::Warning));
+        }
+        table.style_cell(2, config.amount_style(budget.current_funds));
+        table.style_cell(5, config.amount_style(forecast.end_balance));
+    }
+    table.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burn_rate_ok() {
+        let daily = [10.0, 0.0, 20.0];
+        assert_eq!(burn_rate(&daily, &3, &Weighting::Flat), 10.0);
+        assert_eq!(burn_rate(&daily, &3, &Weighting::Linear), 50.0 / 6.0);
+        assert!(burn_rate(&daily, &3, &Weighting::Exponential) < 10.0);
+        assert_eq!(burn_rate(&[], &30, &Weighting::Flat), 0.0);
+    }
+
+    #[test]
+    fn project_ok() {
+        let today = NaiveDate::from_ymd_opt(2024, 2, 20).unwrap();
+        let mut budget = Budget::new("Food", &100.0);
+        let forecast = project(&budget, 10.0, &today);
+        assert_eq!(forecast.exhaustion, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(
+            forecast.period_end,
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert_eq!(forecast.end_balance, 0.0);
+        assert_eq!(forecast.safe_per_day, 10.0);
+        assert!(project(&budget, 0.0, &today).exhaustion.is_none());
+        budget.reduce_funds(&150.0);
+        assert_eq!(project(&budget, 0.0, &today).exhaustion, Some(today));
+    }
+}
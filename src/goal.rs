@@ -0,0 +1,248 @@
+use crate::budget::{progress_bar, Budget};
+use crate::color::Role;
+use crate::config::Config;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::increase_actions;
+use crate::table::{Column, Table};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goal {
+    pub target: f64,
+    pub deadline: NaiveDate,
+    pub started: NaiveDate,
+}
+
+impl Goal {
+    pub fn from_columns(
+        target: Option<f64>,
+        deadline: Option<NaiveDate>,
+        started: Option<NaiveDate>,
+    ) -> Option<Goal> {
+        Some(Goal {
+            target: target?,
+            deadline: deadline?,
+            started: started?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GoalStatus {
+    Reached,
+    OnTrack,
+    Behind,
+    Missed,
+}
+
+impl GoalStatus {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Reached => "reached",
+            Self::OnTrack => "on track",
+            Self::Behind => "behind",
+            Self::Missed => "missed",
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        match self {
+            Self::Reached | Self::OnTrack => Role::Positive,
+            Self::Behind => Role::Warning,
+            Self::Missed => Role::Negative,
+        }
+    }
+}
+
+pub struct GoalProgress {
+    pub saved: f64,
+    pub contributed: f64,
+    pub percent: f64,
+    pub per_month: f64,
+    pub status: GoalStatus,
+}
+
+pub fn add_goal_columns(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute("ALTER TABLE budgets ADD COLUMN target_amount REAL;", ())?;
+    db.execute("ALTER TABLE budgets ADD COLUMN target_date TEXT;", ())?;
+    db.execute("ALTER TABLE budgets ADD COLUMN goal_started TEXT;", ())?;
+    Ok(())
+}
+
+pub fn get_contributions(
+    db: &Connection,
+    id: &u32,
+    since: &DateTime<Utc>,
+) -> Result<f64, rusqlite::Error> {
+    let query = format!(
+        "
+        SELECT COALESCE(SUM(amount), 0.0)
+        FROM records
+        WHERE budget_id = ?1 AND action IN {}
+            AND pending = 0 AND created_at >= ?2;",
+        increase_actions()
+    );
+    db.query_row(&query, (id, since), |row| row.get(0))
+}
+
+pub fn months_left(today: &NaiveDate, deadline: &NaiveDate) -> u32 {
+    if deadline < today {
+        return 0;
+    }
+    let months =
+        (deadline.year() - today.year()) * 12 + deadline.month() as i32 - today.month() as i32;
+    let partial = i32::from(deadline.day() > today.day());
+    (months + partial).max(1) as u32
+}
+
+pub fn track_goal(
+    budget: &Budget,
+    goal: &Goal,
+    contributed: f64,
+    today: &NaiveDate,
+) -> GoalProgress {
+    let saved = budget.current_funds;
+    let remaining = (goal.target - contributed).max(0.0);
+    let total_days = (goal.deadline - goal.started).num_days().max(1) as f64;
+    let elapsed_days = (*today - goal.started)
+        .num_days()
+        .clamp(0, total_days as i64) as f64;
+    let expected = goal.target * elapsed_days / total_days;
+    let status = if contributed >= goal.target {
+        GoalStatus::Reached
+    } else if *today > goal.deadline {
+        GoalStatus::Missed
+    } else if contributed >= expected {
+        GoalStatus::OnTrack
+    } else {
+        GoalStatus::Behind
+    };
+    GoalProgress {
+        saved,
+        contributed,
+        percent: contributed.max(0.0) / goal.target * 100.0,
+        per_month: remaining / f64::from(months_left(today, &goal.deadline).max(1)),
+        status,
+    }
+}
+
+pub fn print_goals(budgets: &[Budget], progress: &HashMap<u32, GoalProgress>, config: &Config) {
+    let rows: Vec<(&Budget, &Goal, &GoalProgress)> = budgets
+        .iter()
+        .filter_map(|budget| {
+            let goal = budget.goal.as_ref()?;
+            Some((budget, goal, progress.get(&budget.budget_id?)?))
+        })
+        .collect();
+    if config.output != OutputFormat::Table {
+        let cells: Vec<Vec<Cell>> = rows
+            .iter()
+            .map(|(budget, goal, progress)| {
+                vec![
+                    Cell::optional_number(budget.budget_id.map(f64::from)),
+                    Cell::text(&budget.name),
+                    Cell::Text(config.currency_code(&budget.currency)),
+                    Cell::Number(progress.saved),
+                    Cell::Number(goal.target),
+                    Cell::Number(progress.percent),
+                    Cell::Number(progress.contributed),
+                    Cell::Text(goal.deadline.to_string()),
+                    Cell::Number(progress.per_month),
+                    Cell::text(progress.status.value()),
+                ]
+            })
+            .collect();
+        let headers = [
+            "id",
+            "budget",
+            "currency",
+            "saved",
+            "target",
+            "percent",
+            "contributed",
+            "deadline",
+            "per_month",
+            "status",
+        ];
+        return print_rows(&config.output, &headers, &cells);
+    }
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("GOAL"),
+        Column::right("SAVED"),
+        Column::right("TARGET"),
+        Column::right("DONE"),
+        Column::left("").keep(),
+        Column::right("ADDED"),
+        Column::left("DEADLINE").keep(),
+        Column::right("PER MONTH"),
+        Column::left("STATUS").keep(),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for (budget, goal, progress) in rows {
+        let money = config.money_format(&budget.currency);
+        table.add_row(vec![
+            budget.budget_id.unwrap().to_string(),
+            budget.name.to_owned(),
+            money.format(progress.saved),
+            money.format(goal.target),
+            format!("{:.0}%", progress.percent),
+            progress_bar(Some(progress.percent), 20),
+            money.format(progress.contributed),
+            goal.deadline.format(&config.date_format).to_string(),
+            money.format(progress.per_month),
+            progress.status.value().to_string(),
+        ]);
+        table.style_cell(2, config.amount_style(progress.saved));
+        table.style_cell(5, config.style(progress.status.role()));
+        table.style_cell(9, config.style(progress.status.role()));
+    }
+    table.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn months_left_ok() {
+        let today = date(2024, 10, 19);
+        assert_eq!(months_left(&today, &date(2024, 10, 31)), 1);
+        assert_eq!(months_left(&today, &date(2024, 11, 19)), 1);
+        assert_eq!(months_left(&today, &date(2024, 12, 31)), 3);
+        assert_eq!(months_left(&today, &date(2024, 10, 1)), 0);
+    }
+
+    #[test]
+    fn track_goal_ok() {
+        let goal = Goal {
+            target: 1200.0,
+            deadline: date(2024, 12, 31),
+            started: date(2024, 1, 1),
+        };
+        let mut budget = Budget::new("Vacation", &2000.0);
+        budget.reduce_funds(&1100.0);
+        let progress = track_goal(&budget, &goal, 0.0, &date(2024, 9, 1));
+        assert_eq!(progress.status, GoalStatus::Behind);
+        assert_eq!(progress.percent, 0.0);
+        assert_eq!(progress.saved, 900.0);
+        let progress = track_goal(&budget, &goal, 900.0, &date(2024, 9, 1));
+        assert_eq!(progress.status, GoalStatus::OnTrack);
+        assert_eq!(progress.percent, 75.0);
+        assert_eq!(progress.per_month, 75.0);
+        let progress = track_goal(&budget, &goal, 900.0, &date(2024, 11, 30));
+        assert_eq!(progress.status, GoalStatus::Behind);
+        let progress = track_goal(&budget, &goal, 900.0, &date(2025, 1, 2));
+        assert_eq!(progress.status, GoalStatus::Missed);
+        budget.increase_funds(&300.0);
+        let progress = track_goal(&budget, &goal, 1200.0, &date(2025, 1, 2));
+        assert_eq!(progress.status, GoalStatus::Reached);
+        assert_eq!(progress.per_month, 0.0);
+    }
+}
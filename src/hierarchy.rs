@@ -0,0 +1,184 @@
+use crate::budget::Budget;
+use crate::record::reduction_actions;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+pub fn add_hierarchy_columns(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        ALTER TABLE budgets ADD COLUMN parent_id INTEGER REFERENCES budgets(budget_id);
+        ALTER TABLE budgets ADD COLUMN spending_cap REAL;";
+    db.execute_batch(query)
+}
+
+pub fn descendant_ids(budgets: &[Budget], id: &u32) -> Vec<u32> {
+    let mut ids = Vec::new();
+    let mut pending = vec![*id];
+    while let Some(parent) = pending.pop() {
+        for budget in budgets {
+            match budget.budget_id {
+                Some(child) if budget.parent_id == Some(parent) && !ids.contains(&child) => {
+                    ids.push(child);
+                    pending.push(child);
+                }
+                _ => (),
+            }
+        }
+    }
+    ids
+}
+
+pub fn roll_up(budgets: &[Budget]) -> Vec<Budget> {
+    budgets
+        .iter()
+        .map(|budget| {
+            let mut total = budget.clone();
+            let below = descendant_ids(budgets, &budget.budget_id.unwrap_or_default());
+            for child in budgets.iter().filter(|child| {
+                child
+                    .budget_id
+                    .is_some_and(|child_id| below.contains(&child_id))
+            }) {
+                total.initial_funds += child.initial_funds;
+                total.current_funds += child.current_funds;
+            }
+            total
+        })
+        .collect()
+}
+
+pub fn arrange(budgets: Vec<Budget>) -> (Vec<Budget>, HashMap<u32, usize>) {
+    let shown: Vec<Option<u32>> = budgets.iter().map(|budget| budget.budget_id).collect();
+    let is_root = |budget: &Budget| budget.parent_id.is_none_or(|id| !shown.contains(&Some(id)));
+    let mut stack: Vec<(usize, usize)> = budgets
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, budget)| is_root(budget))
+        .map(|(index, _)| (index, 0))
+        .collect();
+    let mut order = Vec::new();
+    let mut depths = HashMap::new();
+    while let Some((index, depth)) = stack.pop() {
+        let budget = &budgets[index];
+        if let Some(id) = budget.budget_id {
+            if depths.insert(id, depth).is_some() {
+                continue;
+            }
+        }
+        order.push(index);
+        stack.extend(
+            budgets
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, child)| {
+                    child.parent_id.is_some() && child.parent_id == budget.budget_id
+                })
+                .map(|(child, _)| (child, depth + 1)),
+        );
+    }
+    let mut budgets: Vec<Option<Budget>> = budgets.into_iter().map(Some).collect();
+    let arranged = order
+        .into_iter()
+        .filter_map(|index| budgets[index].take())
+        .collect();
+    (arranged, depths)
+}
+
+pub fn tree_name(name: &str, depth: usize) -> String {
+    match depth {
+        0 => name.to_string(),
+        _ => format!("{}└ {}", "  ".repeat(depth - 1), name),
+    }
+}
+
+pub fn get_reductions(db: &Connection) -> Result<HashMap<u32, f64>, rusqlite::Error> {
+    let query = format!(
+        "
+        SELECT budget_id, SUM(amount)
+        FROM records
+        WHERE action IN {}
+        GROUP BY budget_id;",
+        reduction_actions()
+    );
+    let mut stmt = db.prepare(&query)?;
+    let reductions = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<u32, f64>, rusqlite::Error>>()?;
+    Ok(reductions)
+}
+
+pub fn exceeded_cap<'a>(
+    budgets: &'a [Budget],
+    reductions: &HashMap<u32, f64>,
+    parts: &[(u32, f64)],
+) -> Option<&'a Budget> {
+    budgets
+        .iter()
+        .filter(|budget| budget.spending_cap.is_some())
+        .find(|parent| {
+            let below = descendant_ids(budgets, &parent.budget_id.unwrap_or_default());
+            let added = parts
+                .iter()
+                .filter(|(id, _)| below.contains(id))
+                .fold(0.0, |total, (_, amount)| total + amount);
+            let spent = below
+                .iter()
+                .filter_map(|id| reductions.get(id))
+                .sum::<f64>();
+            added > 0.0 && spent + added > parent.spending_cap.unwrap_or_default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(id: u32, name: &str, funds: f64, parent_id: Option<u32>) -> Budget {
+        let mut budget = Budget::new(name, &funds);
+        budget.budget_id = Some(id);
+        budget.parent_id = parent_id;
+        budget
+    }
+
+    fn household() -> Vec<Budget> {
+        vec![
+            budget(1, "Household", 0.0, None),
+            budget(2, "Groceries", 300.0, Some(1)),
+            budget(3, "Travel", 500.0, None),
+            budget(4, "Utilities", 200.0, Some(1)),
+            budget(5, "Power", 80.0, Some(4)),
+        ]
+    }
+
+    #[test]
+    fn roll_up_ok() {
+        let budgets = roll_up(&household());
+        let funds: Vec<f64> = budgets.iter().map(|budget| budget.current_funds).collect();
+        assert_eq!(funds, [580.0, 300.0, 500.0, 280.0, 80.0]);
+    }
+
+    #[test]
+    fn arrange_ok() {
+        let (budgets, depths) = arrange(household());
+        let ids: Vec<u32> = budgets
+            .iter()
+            .filter_map(|budget| budget.budget_id)
+            .collect();
+        assert_eq!(ids, [1, 2, 4, 5, 3]);
+        assert_eq!(depths[&5], 2);
+        assert_eq!(tree_name("Power", 2), "  └ Power");
+    }
+
+    #[test]
+    fn exceeded_cap_ko() {
+        let mut budgets = household();
+        budgets[0].spending_cap = Some(100.0);
+        budgets[1].increase_funds(&500.0);
+        let reductions = HashMap::from([(2, 60.0)]);
+        assert!(exceeded_cap(&budgets, &reductions, &[(5, 30.0)]).is_none());
+        let parent = exceeded_cap(&budgets, &reductions, &[(2, 30.0), (5, 20.0)]);
+        assert_eq!(parent.and_then(|parent| parent.budget_id), Some(1));
+        assert!(exceeded_cap(&budgets, &reductions, &[(3, 500.0)]).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+pub fn create_income_table(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS income (
+            income_id INTEGER PRIMARY KEY,
+            budget_id INTEGER,
+            amount REAL NOT NULL,
+            description TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (budget_id) REFERENCES budgets(budget_id)
+        );";
+    db.execute(query, ())?;
+    Ok(())
+}
+
+pub fn insert_income(
+    db: &Connection,
+    budget_id: Option<&u32>,
+    amount: &f64,
+    description: &Option<String>,
+    created_at: &DateTime<Utc>,
+    account_id: &Option<u32>,
+) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO income (budget_id, amount, description, created_at, account_id)
+        VALUES (?1, ?2, ?3, ?4, ?5);";
+    db.execute(
+        query,
+        (budget_id, amount, description, created_at, account_id),
+    )
+}
+
+pub fn get_unassigned_income(db: &Connection) -> Result<Option<f64>, rusqlite::Error> {
+    db.query_row("SELECT SUM(amount) FROM income;", [], |row| row.get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{insert_budget, Budget};
+    use crate::database::migrate;
+
+    #[test]
+    fn get_unassigned_income_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_budget(&db, &Budget::new("Food", &0.0)).unwrap();
+        assert_eq!(get_unassigned_income(&db).unwrap(), None);
+        let now = Utc::now();
+        insert_income(&db, None, &1000.0, &None, &now, &None).unwrap();
+        insert_income(&db, Some(&1), &-250.0, &None, &now, &None).unwrap();
+        assert_eq!(get_unassigned_income(&db).unwrap(), Some(750.0));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::utils;
+use chrono::Utc;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+
+pub const SETTLE_ACTION: &str = "Settle pending records";
+
+pub struct Operation<'a> {
+    db: &'a Connection,
+    operation_id: i64,
+    changes: Vec<(i64, String, i64)>,
+}
+
+pub struct OperationSummary {
+    pub operation_id: i64,
+    pub action: String,
+    pub budget_id: Option<u32>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Before,
+    After,
+}
+
+impl State {
+    fn value(&self) -> &str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+        }
+    }
+
+    fn exists_column(&self) -> &str {
+        match self {
+            Self::Before => "before_exists",
+            Self::After => "after_exists",
+        }
+    }
+}
+
+impl<'a> Operation<'a> {
+    pub fn begin(
+        db: &'a Connection,
+        action: &str,
+        budget_id: Option<&u32>,
+    ) -> Result<Operation<'a>, rusqlite::Error> {
+        if action != SETTLE_ACTION {
+            clear_redo_stack(db)?;
+        }
+        let query = "
+            INSERT INTO operations (action, budget_id, undone, created_at)
+            VALUES (?1, ?2, 0, ?3);";
+        db.execute(query, (action, budget_id, Utc::now()))?;
+        Ok(Operation {
+            db,
+            operation_id: db.last_insert_rowid(),
+            changes: Vec::new(),
+        })
+    }
+
+    pub fn track(&mut self, table: &str, row_id: i64) -> Result<(), rusqlite::Error> {
+        if self.is_tracked(table, row_id) {
+            return Ok(());
+        }
+        let change_id = self.insert_change(table, row_id)?;
+        let exists = capture_row(self.db, change_id, State::Before, table, row_id)?;
+        set_exists(self.db, change_id, State::Before, exists)?;
+        self.changes.push((change_id, table.to_string(), row_id));
+        Ok(())
+    }
+
+    pub fn track_new(&mut self, table: &str, row_id: i64) -> Result<(), rusqlite::Error> {
+        if self.is_tracked(table, row_id) {
+            return Ok(());
+        }
+        let change_id = self.insert_change(table, row_id)?;
+        self.changes.push((change_id, table.to_string(), row_id));
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), rusqlite::Error> {
+        for (change_id, table, row_id) in &self.changes {
+            let exists = capture_row(self.db, *change_id, State::After, table, *row_id)?;
+            set_exists(self.db, *change_id, State::After, exists)?;
+        }
+        Ok(())
+    }
+
+    fn is_tracked(&self, table: &str, row_id: i64) -> bool {
+        self.changes
+            .iter()
+            .any(|(_, tracked_table, tracked_id)| tracked_table == table && *tracked_id == row_id)
+    }
+
+    fn insert_change(&self, table: &str, row_id: i64) -> Result<i64, rusqlite::Error> {
+        let query = "
+            INSERT INTO operation_changes (operation_id, table_name, row_id, before_exists, after_exists)
+            VALUES (?1, ?2, ?3, 0, 0);";
+        self.db.execute(query, (self.operation_id, table, row_id))?;
+        Ok(self.db.last_insert_rowid())
+    }
+}
+
+pub fn create_journal_tables(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS operations (
+            operation_id INTEGER PRIMARY KEY,
+            action TEXT NOT NULL,
+            budget_id INTEGER,
+            undone INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT
+        );
+        CREATE TABLE IF NOT EXISTS operation_changes (
+            change_id INTEGER PRIMARY KEY,
+            operation_id INTEGER NOT NULL,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            before_exists INTEGER NOT NULL,
+            after_exists INTEGER NOT NULL,
+            FOREIGN KEY (operation_id) REFERENCES operations(operation_id)
+        );
+        CREATE TABLE IF NOT EXISTS operation_values (
+            change_id INTEGER NOT NULL,
+            state TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            value,
+            FOREIGN KEY (change_id) REFERENCES operation_changes(change_id)
+        );";
+    db.execute_batch(query)
+}
+
+pub fn undo_operations(
+    db: &Connection,
+    count: &u32,
+) -> Result<Vec<OperationSummary>, rusqlite::Error> {
+    let query = "
+        SELECT operation_id, action, budget_id
+        FROM operations
+        WHERE undone = 0
+        ORDER BY operation_id DESC
+        LIMIT 1;";
+    let mut undone = Vec::new();
+    let mut remaining = *count;
+    while remaining > 0 {
+        let operation = match db.query_row(query, [], map_summary).optional()? {
+            Some(operation) => operation,
+            None => break,
+        };
+        apply_state(db, operation.operation_id, State::Before)?;
+        db.execute(
+            "UPDATE operations SET undone = 1 WHERE operation_id = ?1;",
+            [operation.operation_id],
+        )?;
+        if operation.action != SETTLE_ACTION {
+            remaining -= 1;
+        }
+        undone.push(operation);
+    }
+    Ok(undone)
+}
+
+pub fn redo_operations(
+    db: &Connection,
+    count: &u32,
+) -> Result<Vec<OperationSummary>, rusqlite::Error> {
+    let query = "
+        SELECT operation_id, action, budget_id
+        FROM operations
+        WHERE undone = 1
+        ORDER BY operation_id ASC
+        LIMIT 1;";
+    let mut redone = Vec::new();
+    let mut remaining = *count;
+    loop {
+        let operation = match db.query_row(query, [], map_summary).optional()? {
+            Some(operation) => operation,
+            None => break,
+        };
+        if operation.action != SETTLE_ACTION {
+            if remaining == 0 {
+                break;
+            }
+            remaining -= 1;
+        }
+        apply_state(db, operation.operation_id, State::After)?;
+        db.execute(
+            "UPDATE operations SET undone = 0 WHERE operation_id = ?1;",
+            [operation.operation_id],
+        )?;
+        redone.push(operation);
+    }
+    Ok(redone)
+}
+
+pub fn get_operation_budgets(
+    db: &Connection,
+    operation: &OperationSummary,
+) -> Result<Vec<u32>, rusqlite::Error> {
+    let query = "
+        SELECT DISTINCT row_id
+        FROM operation_changes
+        WHERE operation_id = ?1 AND table_name = 'budgets'
+        ORDER BY row_id;";
+    let mut stmt = db.prepare(query)?;
+    let mut ids = stmt
+        .query_map([operation.operation_id], |row| row.get(0))?
+        .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
+    if let Some(id) = operation.budget_id {
+        if !ids.contains(&id) {
+            ids.insert(0, id);
+        }
+    }
+    Ok(ids)
+}
+
+fn map_summary(row: &rusqlite::Row) -> Result<OperationSummary, rusqlite::Error> {
+    Ok(OperationSummary {
+        operation_id: row.get(0)?,
+        action: row.get(1)?,
+        budget_id: row.get(2)?,
+    })
+}
+
+fn clear_redo_stack(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        DELETE FROM operation_values
+        WHERE change_id IN (
+            SELECT c.change_id
+            FROM operation_changes c
+            JOIN operations o
+            ON c.operation_id = o.operation_id
+            WHERE o.undone = 1
+        );
+        DELETE FROM operation_changes
+        WHERE operation_id IN (SELECT operation_id FROM operations WHERE undone = 1);
+        DELETE FROM operations
+        WHERE undone = 1;";
+    db.execute_batch(query)
+}
+
+fn capture_row(
+    db: &Connection,
+    change_id: i64,
+    state: State,
+    table: &str,
+    row_id: i64,
+) -> Result<bool, rusqlite::Error> {
+    let query = format!("SELECT * FROM \"{}\" WHERE rowid = ?1;", table);
+    let mut stmt = db.prepare(&query)?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let values = stmt
+        .query_row([row_id], |row| {
+            (0..columns.len())
+                .map(|index| row.get::<_, Value>(index))
+                .collect::<Result<Vec<Value>, rusqlite::Error>>()
+        })
+        .optional()?;
+    let values = match values {
+        Some(values) => values,
+        None => return Ok(false),
+    };
+    let query = "
+        INSERT INTO operation_values (change_id, state, column_name, value)
+        VALUES (?1, ?2, ?3, ?4);";
+    for (column, value) in columns.iter().zip(values) {
+        db.execute(query, (change_id, state.value(), column, value))?;
+    }
+    Ok(true)
+}
+
+fn set_exists(
+    db: &Connection,
+    change_id: i64,
+    state: State,
+    exists: bool,
+) -> Result<(), rusqlite::Error> {
+    let query = format!(
+        "UPDATE operation_changes SET {} = ?1 WHERE change_id = ?2;",
+        state.exists_column()
+    );
+    db.execute(&query, (exists, change_id))?;
+    Ok(())
+}
+
+fn apply_state(db: &Connection, operation_id: i64, state: State) -> Result<(), rusqlite::Error> {
+    let order = match state {
+        State::Before => "DESC",
+        State::After => "ASC",
+    };
+    let query = format!(
+        "SELECT change_id, table_name, row_id, {}
+        FROM operation_changes
+        WHERE operation_id = ?1
+        ORDER BY change_id {};",
+        state.exists_column(),
+        order
+    );
+    let mut stmt = db.prepare(&query)?;
+    let changes = stmt
+        .query_map([operation_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+    for (change_id, table, row_id, exists) in changes {
+        if exists {
+            restore_row(db, change_id, state, &table, row_id)?;
+        } else {
+            let query = format!("DELETE FROM \"{}\" WHERE rowid = ?1;", table);
+            db.execute(&query, [row_id])?;
+        }
+    }
+    Ok(())
+}
+
+fn restore_row(
+    db: &Connection,
+    change_id: i64,
+    state: State,
+    table: &str,
+    row_id: i64,
+) -> Result<(), rusqlite::Error> {
+    let query = "
+        SELECT column_name, value
+        FROM operation_values
+        WHERE change_id = ?1 AND state = ?2;";
+    let mut stmt = db.prepare(query)?;
+    let (columns, values): (Vec<String>, Vec<Value>) = stmt
+        .query_map((change_id, state.value()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?))
+        })?
+        .collect::<Result<Vec<_>, rusqlite::Error>>()?
+        .into_iter()
+        .unzip();
+    let exists_query = format!("SELECT COUNT(*) FROM \"{}\" WHERE rowid = ?1;", table);
+    let exists: bool = db.query_row(&exists_query, [row_id], |row| row.get(0))?;
+    let query = if exists {
+        let assignments: Vec<String> = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| format!("\"{}\" = ?{}", column, index + 1))
+            .collect();
+        format!(
+            "UPDATE \"{}\" SET {} WHERE rowid = {};",
+            table,
+            assignments.join(", "),
+            row_id
+        )
+    } else {
+        let names: Vec<String> = columns.iter().map(|c| format!("\"{}\"", c)).collect();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        format!(
+            "INSERT INTO \"{}\" ({}) VALUES ({});",
+            table,
+            names.join(", "),
+            placeholders.join(", ")
+        )
+    };
+    db.execute(&query, params_from_iter(values))?;
+    Ok(())
+}
+
+pub fn print_operations(verb: &str, operations: &Vec<OperationSummary>) {
+    if operations.is_empty() {
+        println!("Nothing to {}.", verb);
+        return;
+    }
+    let verb = utils::capitalize(verb);
+    for operation in operations {
+        match operation.budget_id {
+            Some(id) => println!("{}: {} (budget {})", verb, operation.action, id),
+            None => println!("{}: {}", verb, operation.action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{insert_budget, select_all_budgets, update_budget, Budget};
+    use crate::database::migrate;
+
+    fn setup() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        let mut operation = Operation::begin(&db, "Create budget", None).unwrap();
+        insert_budget(&db, &Budget::new("Test", &5000.0)).unwrap();
+        operation
+            .track_new("budgets", db.last_insert_rowid())
+            .unwrap();
+        operation.finish().unwrap();
+        db
+    }
+
+    fn reduce(db: &Connection, amount: &f64) {
+        let mut operation = Operation::begin(db, "Reduce funds", Some(&1)).unwrap();
+        operation.track("budgets", 1).unwrap();
+        let mut budget = select_all_budgets(db).unwrap().remove(0);
+        budget.reduce_funds(amount);
+        update_budget(db, &budget).unwrap();
+        operation.finish().unwrap();
+    }
+
+    #[test]
+    fn undo_ok() {
+        let db = setup();
+        reduce(&db, &3000.0);
+        let undone = undo_operations(&db, &1).unwrap();
+        assert_eq!(undone.len(), 1);
+        assert_eq!(select_all_budgets(&db).unwrap()[0].current_funds, 5000.0);
+    }
+
+    #[test]
+    fn undo_create_ok() {
+        let db = setup();
+        undo_operations(&db, &1).unwrap();
+        assert!(select_all_budgets(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn redo_ok() {
+        let db = setup();
+        reduce(&db, &3000.0);
+        undo_operations(&db, &2).unwrap();
+        let redone = redo_operations(&db, &2).unwrap();
+        assert_eq!(redone.len(), 2);
+        assert_eq!(select_all_budgets(&db).unwrap()[0].current_funds, 2000.0);
+    }
+
+    #[test]
+    fn redo_ko() {
+        let db = setup();
+        reduce(&db, &3000.0);
+        undo_operations(&db, &1).unwrap();
+        reduce(&db, &1000.0);
+        assert!(redo_operations(&db, &1).unwrap().is_empty());
+        assert_eq!(select_all_budgets(&db).unwrap()[0].current_funds, 4000.0);
+    }
+}
@@ -1,6 +1,30 @@
+pub mod account;
+pub mod audit;
+pub mod backup;
 pub mod budget;
+pub mod chart;
 pub mod cli;
+pub mod color;
+pub mod config;
+pub mod currency;
 pub mod database;
+pub mod dates;
+pub mod error;
+pub mod expression;
+pub mod forecast;
+pub mod goal;
+pub mod hierarchy;
+pub mod income;
+pub mod journal;
+pub mod money;
+pub mod output;
+pub mod payee;
+pub mod rates;
+pub mod reconcile;
 pub mod record;
+pub mod report;
 pub mod services;
+pub mod split;
+pub mod table;
+pub mod timeline;
 pub mod utils;
@@ -1,10 +1,26 @@
 use budget_manager::cli;
-use budget_manager::cli::Cli;
+use budget_manager::cli::{Cli, Command};
 use budget_manager::database;
 use clap::Parser;
+use std::process;
 
 fn main() {
-    let Cli { command, database } = Cli::parse();
-    let db = database::open_db(database);
-    cli::run(db, command);
+    let cli = Cli::parse();
+    if let Command::Config { action } = &cli.command {
+        cli::run_config(action);
+        return;
+    }
+    if let Command::Completions { shell } = &cli.command {
+        cli::print_completions(shell);
+        return;
+    }
+    let config = match cli.load_config() {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("Error: {}.", error);
+            process::exit(1);
+        }
+    };
+    let db = database::open_db(config.database.clone());
+    cli::run(db, cli.command, config);
 }
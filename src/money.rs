@@ -0,0 +1,225 @@
+#[derive(Clone, Debug, PartialEq)]
+pub struct Locale {
+    pub thousands_separator: String,
+    pub decimal_separator: String,
+    pub symbol_first: bool,
+}
+
+pub struct MoneyFormat {
+    pub symbol: String,
+    pub precision: usize,
+    pub locale: Locale,
+}
+
+const KNOWN_LOCALES: [(&str, &str, &str, bool); 12] = [
+    ("de-CH", "'", ".", true),
+    ("de-DE", ".", ",", false),
+    ("en-GB", ",", ".", true),
+    ("en-IN", ",", ".", true),
+    ("en-US", ",", ".", true),
+    ("es-CO", ".", ",", true),
+    ("es-ES", ".", ",", false),
+    ("es-MX", ",", ".", true),
+    ("fr-FR", " ", ",", false),
+    ("it-IT", ".", ",", false),
+    ("ja-JP", ",", ".", true),
+    ("pt-BR", ".", ",", true),
+];
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale {
+            thousands_separator: String::from("."),
+            decimal_separator: String::from(","),
+            symbol_first: true,
+        }
+    }
+}
+
+pub fn find_locale(name: &str) -> Option<Locale> {
+    let name = name.replace('_', "-");
+    KNOWN_LOCALES
+        .iter()
+        .find(|(known, _, _, _)| known.eq_ignore_ascii_case(&name))
+        .map(|(_, thousands, decimal, symbol_first)| Locale {
+            thousands_separator: thousands.to_string(),
+            decimal_separator: decimal.to_string(),
+            symbol_first: *symbol_first,
+        })
+}
+
+pub fn locale_names() -> Vec<&'static str> {
+    KNOWN_LOCALES.iter().map(|(name, _, _, _)| *name).collect()
+}
+
+impl MoneyFormat {
+    pub fn format(&self, amount: f64) -> String {
+        let text = format!("{:.*}", self.precision, amount.abs());
+        let (integer, fraction) = match text.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (text.as_str(), None),
+        };
+        let mut grouped = String::new();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                grouped.push_str(&self.locale.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            grouped.push_str(&self.locale.decimal_separator);
+            grouped.push_str(fraction);
+        }
+        let is_zero = text.chars().all(|c| c == '0' || c == '.');
+        let sign = if amount < 0.0 && !is_zero { "-" } else { "" };
+        if self.locale.symbol_first {
+            format!("{}{}{}", sign, self.symbol, grouped)
+        } else {
+            format!("{}{} {}", sign, grouped, self.symbol.trim())
+        }
+    }
+}
+
+pub fn parse_amount(text: &str, locale: &Locale) -> Result<f64, String> {
+    let error = || format!("invalid amount '{}'", text);
+    let mut value = text.trim();
+    let mut negative = false;
+    if let Some(inner) = value.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        negative = true;
+        value = inner.trim();
+    }
+    if let Some(rest) = value.strip_prefix('-') {
+        negative = !negative;
+        value = rest.trim_start();
+    } else if let Some(rest) = value.strip_prefix('+') {
+        value = rest.trim_start();
+    }
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.' || c == ',';
+    let start = value.find(is_number_char).ok_or_else(error)?;
+    let end = value.rfind(is_number_char).ok_or_else(error)? + 1;
+    let is_symbol = |part: &str| {
+        part.trim()
+            .chars()
+            .all(|c| !c.is_ascii_digit() && !c.is_whitespace() && c != '-' && c != '+')
+    };
+    if !is_symbol(&value[..start]) || !is_symbol(&value[end..]) {
+        return Err(error());
+    }
+    let number: String = value[start..end]
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\'' | '_'))
+        .collect();
+    if !number.chars().all(is_number_char) {
+        return Err(error());
+    }
+    let decimal = decimal_position(&number, locale);
+    if !is_grouped(&number[..decimal.unwrap_or(number.len())]) {
+        return Err(error());
+    }
+    let normalised: String = number
+        .char_indices()
+        .filter_map(|(index, c)| match c {
+            '.' | ',' if Some(index) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    let amount: f64 = normalised.parse().map_err(|_| error())?;
+    if !amount.is_finite() {
+        return Err(error());
+    }
+    Ok(if negative { -amount } else { amount })
+}
+
+fn decimal_position(number: &str, locale: &Locale) -> Option<usize> {
+    let separators: Vec<(usize, char)> = number
+        .char_indices()
+        .filter(|(_, c)| *c == '.' || *c == ',')
+        .collect();
+    let (last_index, last) = *separators.last()?;
+    if separators.iter().any(|(_, c)| *c != last) {
+        return Some(last_index);
+    }
+    if separators.len() > 1 {
+        return None;
+    }
+    let digits_after = number.len() - last_index - 1;
+    let is_thousands =
+        locale.thousands_separator.starts_with(last) && !locale.decimal_separator.starts_with(last);
+    if is_thousands && digits_after == 3 {
+        None
+    } else {
+        Some(last_index)
+    }
+}
+
+fn is_grouped(integer: &str) -> bool {
+    let separators: Vec<char> = integer.chars().filter(|c| !c.is_ascii_digit()).collect();
+    let Some(separator) = separators.first() else {
+        return true;
+    };
+    if separators.iter().any(|c| c != separator) {
+        return false;
+    }
+    let mut groups = integer.split(*separator);
+    groups.next().is_some_and(|group| !group.is_empty()) && groups.all(|group| group.len() == 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_amount_ok() {
+        let european = find_locale("es-ES").unwrap();
+        let american = find_locale("en_US").unwrap();
+        assert_eq!(parse_amount("1.234,50", &european), Ok(1234.5));
+        assert_eq!(parse_amount("1.234,50 €", &american), Ok(1234.5));
+        assert_eq!(parse_amount("$1,200", &american), Ok(1200.0));
+        assert_eq!(parse_amount("1,5", &european), Ok(1.5));
+        assert_eq!(parse_amount("12.50", &european), Ok(12.5));
+        assert_eq!(parse_amount("-$3.25", &american), Ok(-3.25));
+        assert_eq!(parse_amount("(4)", &american), Ok(-4.0));
+        assert_eq!(
+            parse_amount("1 234,5", &find_locale("fr-FR").unwrap()),
+            Ok(1234.5)
+        );
+        assert_eq!(parse_amount("USD 1,000,000", &american), Ok(1000000.0));
+        assert_eq!(parse_amount("1.234.567", &european), Ok(1234567.0));
+        assert_eq!(parse_amount(".5", &american), Ok(0.5));
+    }
+
+    #[test]
+    fn parse_amount_ko() {
+        let locale = Locale::default();
+        assert!(parse_amount("", &locale).is_err());
+        assert!(parse_amount("abc", &locale).is_err());
+        assert!(parse_amount("12a3", &locale).is_err());
+        assert!(parse_amount("1 2 x 3", &locale).is_err());
+        assert!(parse_amount("3 4-", &locale).is_err());
+        assert!(parse_amount("1.2.3", &locale).is_err());
+        assert!(parse_amount("1,23,456", &locale).is_err());
+        assert!(parse_amount("1,234.5.6", &locale).is_err());
+        assert!(parse_amount("1.234,567.8", &locale).is_err());
+        assert!(parse_amount(",500", &find_locale("en-US").unwrap()).is_err());
+        assert!(parse_amount("1,5,5", &find_locale("es-ES").unwrap()).is_err());
+    }
+
+    #[test]
+    fn format_ok() {
+        let format = MoneyFormat {
+            symbol: String::from("€"),
+            precision: 2,
+            locale: find_locale("de-DE").unwrap(),
+        };
+        assert_eq!(format.format(1234567.891), "1.234.567,89 €");
+        assert_eq!(format.format(-0.001), "0,00 €");
+        let format = MoneyFormat {
+            symbol: String::from("$"),
+            precision: 0,
+            locale: find_locale("en-US").unwrap(),
+        };
+        assert_eq!(format.format(-1500.0), "-$1,500");
+        assert_eq!(format.format(999.0), "$999");
+    }
+}
@@ -0,0 +1,178 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl OutputFormat {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Table => "table",
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Markdown => "markdown",
+        }
+    }
+}
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl Cell {
+    pub fn text(text: &str) -> Cell {
+        Cell::Text(text.to_string())
+    }
+
+    pub fn optional_text(text: &Option<String>) -> Cell {
+        match text {
+            Some(text) => Cell::Text(text.to_owned()),
+            None => Cell::Empty,
+        }
+    }
+
+    pub fn optional_number(number: Option<f64>) -> Cell {
+        match number {
+            Some(number) => Cell::Number(number),
+            None => Cell::Empty,
+        }
+    }
+
+    fn plain(&self) -> String {
+        match self {
+            Self::Text(text) => text.to_owned(),
+            Self::Number(number) => number.to_string(),
+            Self::Empty => String::new(),
+        }
+    }
+}
+
+pub fn print_rows(format: &OutputFormat, headers: &[&str], rows: &[Vec<Cell>]) {
+    match format {
+        OutputFormat::Json => print!("{}", render_json(headers, rows)),
+        OutputFormat::Csv => print!("{}", render_csv(headers, rows)),
+        OutputFormat::Markdown | OutputFormat::Table => {
+            print!("{}", render_markdown(headers, rows))
+        }
+    }
+}
+
+pub fn render_json(headers: &[&str], rows: &[Vec<Cell>]) -> String {
+    let objects: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<String> = headers
+                .iter()
+                .zip(row)
+                .map(|(header, cell)| {
+                    let value = match cell {
+                        Cell::Text(text) => escape_json(text),
+                        Cell::Number(number) if number.is_finite() => number.to_string(),
+                        Cell::Number(_) | Cell::Empty => String::from("null"),
+                    };
+                    format!("{}: {}", escape_json(header), value)
+                })
+                .collect();
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+    if objects.is_empty() {
+        return String::from("[]\n");
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+pub fn render_csv(headers: &[&str], rows: &[Vec<Cell>]) -> String {
+    let mut output = headers.join(",") + "\n";
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                let text = cell.plain();
+                if text.contains([',', '"', '\n']) {
+                    format!("\"{}\"", text.replace('"', "\"\""))
+                } else {
+                    text
+                }
+            })
+            .collect();
+        output += &(fields.join(",") + "\n");
+    }
+    output
+}
+
+pub fn render_markdown(headers: &[&str], rows: &[Vec<Cell>]) -> String {
+    let mut output = format!("| {} |\n", headers.join(" | "));
+    output += &format!("|{}\n", " --- |".repeat(headers.len()));
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| cell.plain().replace('|', "\\|"))
+            .collect();
+        output += &format!("| {} |\n", fields.join(" | "));
+    }
+    output
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<Vec<Cell>> {
+        vec![vec![
+            Cell::Number(1.0),
+            Cell::text("Food, \"fresh\""),
+            Cell::Empty,
+        ]]
+    }
+
+    #[test]
+    fn render_json_ok() {
+        let json = render_json(&["id", "name", "desc"], &rows());
+        assert_eq!(
+            json,
+            "[\n  {\"id\": 1, \"name\": \"Food, \\\"fresh\\\"\", \"desc\": null}\n]\n"
+        );
+    }
+
+    #[test]
+    fn render_csv_ok() {
+        let csv = render_csv(&["id", "name", "desc"], &rows());
+        assert_eq!(csv, "id,name,desc\n1,\"Food, \"\"fresh\"\"\",\n");
+    }
+
+    #[test]
+    fn render_markdown_ok() {
+        let markdown = render_markdown(
+            &["id", "name"],
+            &[vec![Cell::Number(2.5), Cell::text("a|b")]],
+        );
+        assert_eq!(markdown, "| id | name |\n| --- | --- |\n| 2.5 | a\\|b |\n");
+    }
+}
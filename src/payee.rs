@@ -0,0 +1,292 @@
+use crate::color::Role;
+use crate::config::Config;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::reduction_actions;
+use crate::table::{Column, Table};
+use crate::utils;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+const NOISE_WORDS: [&str; 9] = [
+    "store",
+    "shop",
+    "supermarket",
+    "inc",
+    "ltd",
+    "llc",
+    "co",
+    "sa",
+    "sl",
+];
+
+pub struct Payee {
+    pub payee_id: u32,
+    pub name: String,
+    pub aliases: Option<String>,
+    pub uses: u32,
+}
+
+pub struct PayeeTotal {
+    pub name: String,
+    pub currency: Option<String>,
+    pub count: u32,
+    pub total: f64,
+}
+
+pub fn create_payee_tables(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS payees (
+            payee_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE IF NOT EXISTS payee_aliases (
+            alias TEXT PRIMARY KEY,
+            payee_id INTEGER NOT NULL REFERENCES payees(payee_id)
+        );
+        ALTER TABLE records ADD COLUMN payee_id INTEGER REFERENCES payees(payee_id);
+        CREATE INDEX IF NOT EXISTS records_payee ON records (payee_id);";
+    db.execute_batch(query)
+}
+
+pub fn normalize_payee(text: &str) -> String {
+    let cleaned: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || "&'#-".contains(c) => c,
+            _ => ' ',
+        })
+        .collect();
+    let is_number = |word: &str| {
+        word.trim_start_matches('#')
+            .chars()
+            .all(|c| c.is_ascii_digit())
+    };
+    let mut words: Vec<&str> = cleaned
+        .split_whitespace()
+        .map(|word| word.trim_matches('-'))
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .filter(|(index, word)| *index == 0 || !is_number(word))
+        .map(|(_, word)| word)
+        .collect();
+    while words.len() > 1 && words.last().is_some_and(|word| NOISE_WORDS.contains(word)) {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+pub fn find_payee(payees: &[Payee], text: &str) -> Option<u32> {
+    let wanted = normalize_payee(text);
+    payees
+        .iter()
+        .find(|payee| {
+            normalize_payee(&payee.name) == wanted
+                || payee
+                    .aliases
+                    .as_ref()
+                    .is_some_and(|aliases| aliases.split(", ").any(|alias| alias == wanted))
+        })
+        .map(|payee| payee.payee_id)
+}
+
+pub fn payee_name(text: &str) -> String {
+    utils::capitalize(&normalize_payee(text))
+}
+
+pub fn insert_payee(db: &Connection, name: &str) -> Result<usize, rusqlite::Error> {
+    db.execute("INSERT INTO payees (name) VALUES (?1);", [name])
+}
+
+pub fn insert_payee_alias(
+    db: &Connection,
+    payee_id: &u32,
+    alias: &str,
+) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO payee_aliases (alias, payee_id)
+        VALUES (?1, ?2);";
+    db.execute(query, (alias, payee_id))
+}
+
+pub fn select_all_payees(db: &Connection) -> Result<Vec<Payee>, rusqlite::Error> {
+    let query = "
+        SELECT p.payee_id, p.name,
+            (SELECT GROUP_CONCAT(alias, ', ') FROM payee_aliases a WHERE a.payee_id = p.payee_id),
+            (SELECT COUNT(*) FROM records t WHERE t.payee_id = p.payee_id) AS uses
+        FROM payees p
+        ORDER BY uses DESC, p.name;";
+    let mut stmt = db.prepare(query)?;
+    let payees = stmt
+        .query_map([], |row| {
+            Ok(Payee {
+                payee_id: row.get(0)?,
+                name: row.get(1)?,
+                aliases: row.get(2)?,
+                uses: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<Payee>, rusqlite::Error>>()?;
+    Ok(payees)
+}
+
+pub fn get_payee_totals(
+    db: &Connection,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+) -> Result<Vec<PayeeTotal>, rusqlite::Error> {
+    let query = format!(
+        "
+        SELECT p.name, b.currency, COUNT(*), SUM(t.amount) AS total
+        FROM records t
+        JOIN payees p
+        ON t.payee_id = p.payee_id
+        JOIN budgets b
+        ON t.budget_id = b.budget_id
+        WHERE t.action IN {} AND t.pending = 0
+            AND t.created_at >= ?1 AND t.created_at < ?2
+        GROUP BY p.payee_id, b.currency
+        ORDER BY total DESC, p.name;",
+        reduction_actions()
+    );
+    let mut stmt = db.prepare(&query)?;
+    let totals = stmt
+        .query_map((from, to), |row| {
+            Ok(PayeeTotal {
+                name: row.get(0)?,
+                currency: row.get(1)?,
+                count: row.get(2)?,
+                total: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<PayeeTotal>, rusqlite::Error>>()?;
+    Ok(totals)
+}
+
+pub fn print_payees(payees: &[Payee], config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = payees
+            .iter()
+            .map(|payee| {
+                vec![
+                    Cell::Number(payee.payee_id.into()),
+                    Cell::text(&payee.name),
+                    Cell::optional_text(&payee.aliases),
+                    Cell::Number(payee.uses.into()),
+                ]
+            })
+            .collect();
+        let headers = ["id", "payee", "aliases", "records"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("PAYEE"),
+        Column::left("ALIASES").wrap(),
+        Column::right("RECORDS"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for payee in payees {
+        table.add_row(vec![
+            payee.payee_id.to_string(),
+            payee.name.to_owned(),
+            payee.aliases.clone().unwrap_or_default(),
+            payee.uses.to_string(),
+        ]);
+    }
+    table.print();
+}
+
+pub fn print_payee_report(totals: &[PayeeTotal], period: &str, config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = totals
+            .iter()
+            .map(|total| {
+                vec![
+                    Cell::text(period),
+                    Cell::text(&total.name),
+                    Cell::Text(config.currency_code(&total.currency)),
+                    Cell::Number(total.count.into()),
+                    Cell::Number(total.total),
+                ]
+            })
+            .collect();
+        let headers = ["period", "payee", "currency", "records", "spent"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    println!("\nSpending by payee for {}", period);
+    let mut table = Table::new(vec![
+        Column::left("PAYEE"),
+        Column::left("CUR").keep(),
+        Column::right("RECORDS"),
+        Column::right("SPENT"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for total in totals {
+        let money = config.money_format(&total.currency);
+        table.add_row(vec![
+            total.name.to_owned(),
+            config.currency_code(&total.currency),
+            total.count.to_string(),
+            money.format(total.total),
+        ]);
+        table.style_cell(3, config.style(Role::Negative));
+    }
+    table.print();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payee(payee_id: u32, name: &str, aliases: Option<&str>) -> Payee {
+        Payee {
+            payee_id,
+            name: name.to_string(),
+            aliases: aliases.map(String::from),
+            uses: 0,
+        }
+    }
+
+    #[test]
+    fn normalize_payee_ok() {
+        assert_eq!(normalize_payee("Lidl"), "lidl");
+        assert_eq!(normalize_payee("lidl store"), "lidl");
+        assert_eq!(normalize_payee("LIDL 0442"), "lidl");
+        assert_eq!(
+            normalize_payee("Marks & Spencer #12, Ltd."),
+            "marks & spencer"
+        );
+        assert_eq!(payee_name("CORNER CAFE 7"), "Corner cafe");
+        assert_eq!(normalize_payee("7-Eleven"), "7-eleven");
+        assert_eq!(normalize_payee("7 Eleven 0042"), "7 eleven");
+        assert_eq!(normalize_payee("Co-op"), "co-op");
+        assert_eq!(normalize_payee("The Co"), "the");
+        assert_eq!(normalize_payee("Co"), "co");
+        assert_eq!(normalize_payee("Store 21 Outlet"), "store outlet");
+    }
+
+    #[test]
+    fn find_payee_ok() {
+        let payees = [
+            payee(1, "Lidl", Some("lidl plus")),
+            payee(2, "Corner cafe", None),
+            payee(3, "7-eleven", None),
+        ];
+        assert_eq!(find_payee(&payees, "LIDL 0442"), Some(1));
+        assert_eq!(find_payee(&payees, "Lidl Plus"), Some(1));
+        assert_eq!(find_payee(&payees, "Corner Cafe"), Some(2));
+        assert_eq!(find_payee(&payees, "7-Eleven #1"), Some(3));
+    }
+
+    #[test]
+    fn find_payee_ko() {
+        let payees = [
+            payee(1, "Corner cafe", None),
+            payee(2, "Corner bakery", None),
+        ];
+        assert_eq!(find_payee(&payees, "corner"), None);
+        assert_eq!(find_payee(&payees, "corner c"), None);
+        assert_eq!(find_payee(&payees, "eleven"), None);
+    }
+}
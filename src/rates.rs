@@ -0,0 +1,224 @@
+use crate::color::Role;
+use crate::config::Config;
+use crate::currency::parse_currency_code;
+use crate::dates::format_datetime;
+use crate::error::Error;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::table::{Column, Table};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+
+pub struct ExchangeRate {
+    pub base: String,
+    pub quote: String,
+    pub rate: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ExchangeRate {
+    pub fn new(base: &str, quote: &str, rate: &f64) -> ExchangeRate {
+        ExchangeRate {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            rate: *rate,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+pub fn print_rates(rates: &Vec<ExchangeRate>, config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = rates
+            .iter()
+            .map(|rate| {
+                vec![
+                    Cell::text(&rate.base),
+                    Cell::text(&rate.quote),
+                    Cell::Number(rate.rate),
+                    Cell::Text(rate.updated_at.to_rfc3339()),
+                ]
+            })
+            .collect();
+        let headers = ["from", "to", "rate", "updated_at"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let mut table = Table::new(vec![
+        Column::left("FROM").keep(),
+        Column::left("TO").keep(),
+        Column::right("RATE"),
+        Column::left("UPDATED").keep(),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for rate in rates {
+        table.add_row(vec![
+            rate.base.to_owned(),
+            rate.quote.to_owned(),
+            rate.rate.to_string(),
+            format_datetime(&rate.updated_at, &config.tz(), &config.datetime_format()),
+        ]);
+    }
+    table.print();
+}
+
+pub fn create_rates_table(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            rate_id INTEGER PRIMARY KEY,
+            base TEXT NOT NULL,
+            quote TEXT NOT NULL,
+            rate REAL NOT NULL,
+            updated_at TEXT,
+            UNIQUE (base, quote)
+        );";
+    db.execute(query, ())?;
+    Ok(())
+}
+
+pub fn get_rate_row_id(
+    db: &Connection,
+    base: &str,
+    quote: &str,
+) -> Result<Option<i64>, rusqlite::Error> {
+    let query = "
+        SELECT rate_id
+        FROM exchange_rates
+        WHERE base = ?1 AND quote = ?2;";
+    db.query_row(query, (base, quote), |row| row.get(0))
+        .optional()
+}
+
+pub fn upsert_rate(db: &Connection, rate: &ExchangeRate) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO exchange_rates (base, quote, rate, updated_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (base, quote) DO UPDATE
+        SET rate = excluded.rate,
+            updated_at = excluded.updated_at;";
+    db.execute(
+        query,
+        (&rate.base, &rate.quote, &rate.rate, &rate.updated_at),
+    )
+}
+
+pub fn get_all_rates(db: &Connection) -> Result<Vec<ExchangeRate>, rusqlite::Error> {
+    let query = "
+        SELECT base, quote, rate, updated_at
+        FROM exchange_rates
+        ORDER BY base, quote;";
+    let mut stmt = db.prepare(query)?;
+    let rate_iter = stmt.query_map([], |row| {
+        Ok(ExchangeRate {
+            base: row.get(0)?,
+            quote: row.get(1)?,
+            rate: row.get(2)?,
+            updated_at: row.get(3)?,
+        })
+    })?;
+    let mut rates = Vec::new();
+    for rate in rate_iter {
+        rates.push(rate?);
+    }
+    Ok(rates)
+}
+
+pub fn find_rate(db: &Connection, from: &str, to: &str) -> Result<Option<f64>, rusqlite::Error> {
+    if from == to {
+        return Ok(Some(1.0));
+    }
+    let rates = get_all_rates(db)?;
+    let direct = |from: &str, to: &str| {
+        rates.iter().find_map(|rate| {
+            if rate.base == from && rate.quote == to {
+                Some(rate.rate)
+            } else if rate.base == to && rate.quote == from && rate.rate != 0.0 {
+                Some(1.0 / rate.rate)
+            } else {
+                None
+            }
+        })
+    };
+    if let Some(rate) = direct(from, to) {
+        return Ok(Some(rate));
+    }
+    let crossed = rates
+        .iter()
+        .flat_map(|rate| [rate.base.as_str(), rate.quote.as_str()])
+        .filter(|via| *via != from && *via != to)
+        .find_map(|via| Some(direct(from, via)? * direct(via, to)?));
+    Ok(crossed)
+}
+
+pub fn parse_rates_csv(text: &str) -> Result<Vec<ExchangeRate>, Error> {
+    let mut rates = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        let invalid = || {
+            Error::Invalid(format!(
+                "line {}: expected FROM,TO,RATE but found '{}'",
+                index + 1,
+                line
+            ))
+        };
+        if fields.len() != 3 {
+            return Err(invalid());
+        }
+        if index == 0 && parse_currency_code(fields[0]).is_err() {
+            continue;
+        }
+        let rate = match fields[2].parse::<f64>() {
+            Ok(rate) if rate.is_finite() && rate > 0.0 => rate,
+            _ => return Err(invalid()),
+        };
+        let base = parse_currency_code(fields[0]).map_err(|_| invalid())?;
+        let quote = parse_currency_code(fields[1]).map_err(|_| invalid())?;
+        rates.push(ExchangeRate::new(&base, &quote, &rate));
+    }
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        create_rates_table(&db).unwrap();
+        upsert_rate(&db, &ExchangeRate::new("EUR", "USD", &1.25)).unwrap();
+        upsert_rate(&db, &ExchangeRate::new("USD", "JPY", &150.0)).unwrap();
+        db
+    }
+
+    #[test]
+    fn find_rate_ok() {
+        let db = setup();
+        assert_eq!(find_rate(&db, "EUR", "USD").unwrap(), Some(1.25));
+        assert_eq!(find_rate(&db, "USD", "EUR").unwrap(), Some(0.8));
+        assert_eq!(find_rate(&db, "EUR", "JPY").unwrap(), Some(187.5));
+    }
+
+    #[test]
+    fn find_rate_ko() {
+        let db = setup();
+        assert_eq!(find_rate(&db, "EUR", "GBP").unwrap(), None);
+    }
+
+    #[test]
+    fn parse_rates_csv_ok() {
+        let rates = parse_rates_csv("from,to,rate\neur,usd,1.08\n\nGBP,USD,1.27\n").unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].base, "EUR");
+        assert_eq!(rates[1].rate, 1.27);
+    }
+
+    #[test]
+    fn parse_rates_csv_ko() {
+        assert!(parse_rates_csv("EUR,USD,1.08\nGBP,USD,abc\n").is_err());
+        assert!(parse_rates_csv("EUR;USD;1.08\n").is_err());
+        assert!(parse_rates_csv("EUR,USD,0\nGBP,USD,1.27\n").is_err());
+        assert!(parse_rates_csv("EUR,USD,abc\n").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use crate::color::{paint, Role};
+use crate::config::Config;
+use crate::dates::format_datetime;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::Record;
+use crate::table::{Column, Table};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClearStatus {
+    Uncleared,
+    Cleared,
+    Reconciled,
+}
+
+impl ClearStatus {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Uncleared => "uncleared",
+            Self::Cleared => "cleared",
+            Self::Reconciled => "reconciled",
+        }
+    }
+
+    pub fn from_value(text: &str) -> ClearStatus {
+        match text {
+            "cleared" => Self::Cleared,
+            "reconciled" => Self::Reconciled,
+            _ => Self::Uncleared,
+        }
+    }
+}
+
+pub struct Reconciliation {
+    pub name: String,
+    pub currency: Option<String>,
+    pub as_of: DateTime<Utc>,
+    pub statement_balance: f64,
+    pub balance: f64,
+    pub records: Vec<Record>,
+}
+
+impl Reconciliation {
+    pub fn cleared_balance(&self) -> f64 {
+        self.records
+            .iter()
+            .filter(|record| record.cleared == ClearStatus::Uncleared)
+            .fold(self.balance, |balance, record| {
+                balance - record.balance_after.unwrap_or_default()
+                    + record.balance_before.unwrap_or_default()
+            })
+    }
+
+    pub fn difference(&self) -> f64 {
+        self.statement_balance - self.cleared_balance()
+    }
+}
+
+pub fn add_status_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "ALTER TABLE records ADD COLUMN status TEXT NOT NULL DEFAULT 'uncleared';",
+        (),
+    )?;
+    Ok(())
+}
+
+pub fn set_record_status(
+    db: &Connection,
+    id: &u32,
+    status: &ClearStatus,
+) -> Result<usize, rusqlite::Error> {
+    let query = "
+        UPDATE records
+        SET status = ?1
+        WHERE record_id = ?2;";
+    db.execute(query, (status.value(), id))
+}
+
+pub fn last_reconciled_at(
+    db: &Connection,
+    budget_id: &u32,
+) -> Result<Option<DateTime<Utc>>, rusqlite::Error> {
+    let query = "
+        SELECT MAX(created_at)
+        FROM records
+        WHERE budget_id = ?1 AND status = 'reconciled';";
+    db.query_row(query, [budget_id], |row| row.get(0))
+}
+
+pub fn last_reconciled_account_at(
+    db: &Connection,
+    account_id: &u32,
+) -> Result<Option<DateTime<Utc>>, rusqlite::Error> {
+    let query = "
+        SELECT MAX(created_at)
+        FROM records
+        WHERE account_id = ?1 AND status = 'reconciled';";
+    db.query_row(query, [account_id], |row| row.get(0))
+}
+
+pub fn get_change_after(
+    db: &Connection,
+    budget_id: &u32,
+    instant: &DateTime<Utc>,
+) -> Result<f64, rusqlite::Error> {
+    let query = "
+        SELECT COALESCE(SUM(balance_after - balance_before), 0)
+        FROM records
+        WHERE budget_id = ?1 AND created_at > ?2 AND pending = 0;";
+    db.query_row(query, (budget_id, instant), |row| row.get(0))
+}
+
+pub fn get_account_change_after(
+    db: &Connection,
+    account_id: &u32,
+    instant: &DateTime<Utc>,
+) -> Result<f64, rusqlite::Error> {
+    let query = "
+        SELECT COALESCE((
+            SELECT SUM(CASE action
+                WHEN 'Increase funds' THEN amount
+                WHEN 'Reduce funds' THEN -amount
+                ELSE 0 END)
+            FROM records
+            WHERE account_id = ?1 AND created_at > ?2 AND pending = 0
+        ), 0) + COALESCE((
+            SELECT SUM(amount)
+            FROM income
+            WHERE account_id = ?1 AND created_at > ?2
+        ), 0);";
+    db.query_row(query, (account_id, instant), |row| row.get(0))
+}
+
+pub fn print_reconciliation(reconciliation: &Reconciliation, config: &Config) {
+    let tz = config.tz();
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = reconciliation
+            .records
+            .iter()
+            .map(|record| {
+                vec![
+                    Cell::optional_number(record.record_id.map(f64::from)),
+                    Cell::Text(record.created_at.to_rfc3339()),
+                    Cell::text(&record.action),
+                    Cell::Number(record.amount),
+                    Cell::optional_text(&record.desc),
+                    Cell::text(record.cleared.value()),
+                ]
+            })
+            .collect();
+        let headers = ["id", "date", "action", "amount", "description", "cleared"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let money = config.money_format(&reconciliation.currency);
+    println!(
+        "\n{} as of {}",
+        reconciliation.name,
+        format_datetime(&reconciliation.as_of, &tz, &config.date_format)
+    );
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("DATE").keep(),
+        Column::left("ACTION"),
+        Column::right("VALUE"),
+        Column::left("CLEARED").keep(),
+        Column::left("DESCRIPTION").wrap(),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for record in &reconciliation.records {
+        let change =
+            record.balance_after.unwrap_or_default() - record.balance_before.unwrap_or_default();
+        table.add_row(vec![
+            record.record_id.unwrap_or_default().to_string(),
+            format_datetime(&record.created_at, &tz, &config.date_format),
+            record.action.to_owned(),
+            money.format(change),
+            String::from(match record.cleared {
+                ClearStatus::Cleared => "yes",
+                _ => "",
+            }),
+            record.desc.clone().unwrap_or_default(),
+        ]);
+        table.style_cell(3, config.amount_style(change));
+    }
+    table.print();
+    let difference = reconciliation.difference();
+    println!();
+    println!(
+        "Statement balance: {}",
+        money.format(reconciliation.statement_balance)
+    );
+    println!(
+        "Cleared balance:   {}",
+        money.format(reconciliation.cleared_balance())
+    );
+    let text = format!("Difference:        {}", money.format(difference));
+    if difference.abs() < 0.005 {
+        println!("{}", text);
+    } else {
+        println!("{}", paint(&text, config.style(Role::Warning)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: u32, before: f64, after: f64, cleared: ClearStatus) -> Record {
+        let mut record = Record::new(&1, "Reduce funds", &(before - after), &None);
+        record.record_id = Some(id);
+        record.balance_before = Some(before);
+        record.balance_after = Some(after);
+        record.cleared = cleared;
+        record
+    }
+
+    #[test]
+    fn difference_ok() {
+        let reconciliation = Reconciliation {
+            name: String::from("Food"),
+            currency: None,
+            as_of: Utc::now(),
+            statement_balance: 450.0,
+            balance: 420.0,
+            records: vec![
+                record(1, 500.0, 450.0, ClearStatus::Cleared),
+                record(2, 450.0, 420.0, ClearStatus::Uncleared),
+            ],
+        };
+        assert_eq!(reconciliation.cleared_balance(), 450.0);
+        assert_eq!(reconciliation.difference(), 0.0);
+        assert_eq!(
+            ClearStatus::from_value("reconciled"),
+            ClearStatus::Reconciled
+        );
+    }
+}
@@ -1,7 +1,15 @@
+use crate::color::Role;
+use crate::config::Config;
+use crate::dates::format_datetime;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::reconcile::ClearStatus;
+use crate::table::{Column, Table};
 use crate::utils;
-use accounting::Accounting;
-use chrono::{DateTime, Local};
-use rusqlite::Connection;
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+
+pub const INCREASES: [&str; 3] = ["Increase funds", "Transfer in", "Assign funds"];
+pub const REDUCTIONS: [&str; 2] = ["Reduce funds", "Transfer out"];
 
 pub struct Record {
     pub record_id: Option<u32>,
@@ -10,7 +18,19 @@ pub struct Record {
     pub action: String,
     pub amount: f64,
     pub desc: Option<String>,
-    pub created_at: DateTime<Local>,
+    pub created_at: DateTime<Utc>,
+    pub balance_before: Option<f64>,
+    pub balance_after: Option<f64>,
+    pub pending: bool,
+    pub rate: Option<f64>,
+    pub currency: Option<String>,
+    pub expression: Option<String>,
+    pub split_id: Option<u32>,
+    pub account_id: Option<u32>,
+    pub account_name: Option<String>,
+    pub cleared: ClearStatus,
+    pub payee_id: Option<u32>,
+    pub payee_name: Option<String>,
 }
 
 impl Record {
@@ -22,36 +42,236 @@ impl Record {
             action: utils::capitalize(action),
             amount: *amount,
             desc: desc.to_owned(),
-            created_at: Local::now(),
+            created_at: Utc::now(),
+            balance_before: None,
+            balance_after: None,
+            pending: false,
+            rate: None,
+            currency: None,
+            expression: None,
+            split_id: None,
+            account_id: None,
+            account_name: None,
+            cleared: ClearStatus::Uncleared,
+            payee_id: None,
+            payee_name: None,
         }
     }
 }
 
-pub fn print_records(records: &Vec<Record>) {
-    let ac = Accounting::new_from_seperator("$", 2, ".", ",");
-    println!(
-        "\n{:<15}{:<20}{:<20}{:<25}{:<25}\n{:-^110}",
-        "DATE", "BUDGET", "ACTION", "VALUE", "DESCRIPTION", ""
-    );
-    for record in records {
-        let bud_name = match &record.budget_name {
-            Some(text) => text.to_owned(),
-            None => String::new(),
+pub fn increase_actions() -> String {
+    action_list(&INCREASES)
+}
+
+pub fn reduction_actions() -> String {
+    action_list(&REDUCTIONS)
+}
+
+fn action_list(actions: &[&str]) -> String {
+    let actions: Vec<String> = actions
+        .iter()
+        .map(|action| format!("'{}'", action))
+        .collect();
+    format!("({})", actions.join(", "))
+}
+
+pub fn apply_action(action: &str, amount: &f64, balance: Option<f64>) -> Option<f64> {
+    match action {
+        "Increase funds" | "Transfer in" | "Assign funds" => {
+            balance.map(|balance| balance + amount)
+        }
+        "Reduce funds" | "Transfer out" => balance.map(|balance| balance - amount),
+        "Set current funds" | "Reset funds" => Some(*amount),
+        _ => balance,
+    }
+}
+
+fn revert_action(action: &str, amount: &f64, balance: Option<f64>) -> Option<f64> {
+    match action {
+        "Increase funds" | "Transfer in" | "Assign funds" => {
+            balance.map(|balance| balance - amount)
+        }
+        "Reduce funds" | "Transfer out" => balance.map(|balance| balance + amount),
+        "Set current funds" | "Reset funds" => None,
+        _ => balance,
+    }
+}
+
+pub fn compute_balances(
+    opening: Option<f64>,
+    closing: f64,
+    actions: &[(String, f64)],
+) -> Vec<(Option<f64>, Option<f64>)> {
+    let mut balances = vec![(None, None); actions.len()];
+    let mut running = Some(closing);
+    for (index, (action, amount)) in actions.iter().enumerate().rev() {
+        balances[index].1 = running;
+        running = revert_action(action, amount, running);
+        balances[index].0 = running;
+    }
+    let mut running = opening;
+    for (index, (action, amount)) in actions.iter().enumerate() {
+        let (before, after) = &mut balances[index];
+        if before.is_none() {
+            *before = running;
+        }
+        if after.is_none() {
+            *after = apply_action(action, amount, *before);
+        }
+        running = *after;
+    }
+    balances
+}
+
+pub fn print_records(records: &[Record], config: &Config) {
+    let tz = config.tz();
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = records
+            .iter()
+            .map(|record| {
+                vec![
+                    Cell::Text(record.created_at.to_rfc3339()),
+                    Cell::optional_text(&record.budget_name),
+                    Cell::text(&record.action),
+                    Cell::Number(record.amount),
+                    Cell::optional_number(record.balance_before),
+                    Cell::optional_number(record.balance_after),
+                    Cell::optional_text(&record.desc),
+                    Cell::text(if record.pending { "pending" } else { "settled" }),
+                    Cell::Text(config.currency_code(&record.currency)),
+                    Cell::optional_number(record.rate),
+                    Cell::optional_text(&record.expression),
+                    Cell::optional_number(record.split_id.map(f64::from)),
+                    Cell::optional_text(&record.account_name),
+                    Cell::text(record.cleared.value()),
+                    Cell::optional_text(&record.payee_name),
+                ]
+            })
+            .collect();
+        let headers = [
+            "date",
+            "budget",
+            "action",
+            "amount",
+            "balance_before",
+            "balance_after",
+            "description",
+            "status",
+            "currency",
+            "rate",
+            "expression",
+            "split",
+            "account",
+            "cleared",
+            "payee",
+        ];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    let mut table = Table::new(vec![
+        Column::left("DATE").keep(),
+        Column::left("BUDGET"),
+        Column::left("ACTION"),
+        Column::right("VALUE"),
+        Column::right("BEFORE"),
+        Column::right("AFTER"),
+        Column::right("RATE"),
+        Column::left("ACCOUNT"),
+        Column::left("PAYEE"),
+        Column::left("DESCRIPTION").wrap(),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    let mut grouped = false;
+    for (index, record) in records.iter().enumerate() {
+        let money = config.money_format(&record.currency);
+        let starts_split = record.split_id.is_some()
+            && (index == 0 || records[index - 1].split_id != record.split_id);
+        if starts_split {
+            let parts: Vec<&Record> = records[index..]
+                .iter()
+                .take_while(|part| part.split_id == record.split_id)
+                .collect();
+            grouped = parts.len() > 1;
+            if grouped {
+                let same_currency = parts.iter().all(|part| part.currency == record.currency);
+                let total = parts.iter().fold(0.0, |total, part| total + part.amount);
+                table.add_row(vec![
+                    format_datetime(&record.created_at, &tz, &config.date_format),
+                    format!("Split #{}", record.split_id.unwrap()),
+                    String::from("Split"),
+                    if same_currency {
+                        money.format(total)
+                    } else {
+                        String::new()
+                    },
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    record.account_name.clone().unwrap_or_default(),
+                    record.payee_name.clone().unwrap_or_default(),
+                    record.desc.clone().unwrap_or_default(),
+                ]);
+                table.style_cell(3, config.style(Role::Negative));
+            }
+        } else if record.split_id.is_none() {
+            grouped = false;
+        }
+        let rate = record
+            .rate
+            .map(|rate| format!("{:.4}", rate))
+            .unwrap_or_default();
+        let desc = match (&record.desc, &record.expression) {
+            (Some(text), Some(expression)) => format!("{} ({})", text, expression),
+            (Some(text), None) => text.to_owned(),
+            (None, Some(expression)) => format!("({})", expression),
+            (None, None) => String::new(),
         };
-        let desc = match &record.desc {
-            Some(text) => text.to_owned(),
-            None => String::new(),
+        let format_balance = |balance: Option<f64>| match (balance, record.pending) {
+            (_, true) => String::from("Pending"),
+            (Some(balance), false) => money.format(balance),
+            (None, false) => String::from("-"),
         };
-        let date = record.created_at.format("%d-%m-%Y");
-        println!(
-            "{:<15}{:<20}{:<20}{:<25}{:<25}",
+        let budget_name = record.budget_name.clone().unwrap_or_default();
+        let (date, budget_name, desc) = match (grouped, record.split_id) {
+            (true, _) => (String::new(), format!("└ {}", budget_name), String::new()),
+            (false, Some(split_id)) => (
+                format_datetime(&record.created_at, &tz, &config.date_format),
+                budget_name,
+                format!("[split #{}] {}", split_id, desc)
+                    .trim_end()
+                    .to_string(),
+            ),
+            (false, None) => (
+                format_datetime(&record.created_at, &tz, &config.date_format),
+                budget_name,
+                desc,
+            ),
+        };
+        table.add_row(vec![
             date,
-            bud_name,
-            record.action,
-            ac.format_money(record.amount),
+            budget_name,
+            record.action.to_owned(),
+            money.format(record.amount),
+            format_balance(record.balance_before),
+            format_balance(record.balance_after),
+            rate,
+            record.account_name.clone().unwrap_or_default(),
+            record.payee_name.clone().unwrap_or_default(),
             desc,
-        )
+        ]);
+        let role = match record.action.as_str() {
+            "Increase funds" | "Transfer in" | "Assign funds" => Some(Role::Positive),
+            "Reduce funds" | "Transfer out" => Some(Role::Negative),
+            _ => None,
+        };
+        table.style_cell(3, role.and_then(|role| config.style(role)));
+        if !record.pending {
+            table.style_cell(
+                5,
+                config.amount_style(record.balance_after.unwrap_or_default()),
+            );
+        }
     }
+    table.print();
 }
 
 pub fn create_record_table(db: &Connection) -> Result<usize, rusqlite::Error> {
@@ -67,10 +287,92 @@ pub fn create_record_table(db: &Connection) -> Result<usize, rusqlite::Error> {
         );";
     db.execute(query, ())
 }
+
+pub fn add_balance_columns(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        ALTER TABLE records ADD COLUMN balance_before REAL;
+        ALTER TABLE records ADD COLUMN balance_after REAL;";
+    db.execute_batch(query)?;
+    backfill_balances(db)
+}
+
+fn backfill_balances(db: &Connection) -> Result<(), rusqlite::Error> {
+    let mut budget_stmt = db.prepare(
+        "
+        SELECT b.budget_id, b.initial_funds, b.current_funds,
+            EXISTS (
+                SELECT 1
+                FROM records t
+                WHERE t.budget_id = b.budget_id AND t.action = 'Set initial funds'
+            )
+        FROM budgets b;",
+    )?;
+    let budgets = budget_stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+    let mut record_stmt = db.prepare(
+        "
+        SELECT record_id, action, amount
+        FROM records
+        WHERE budget_id = ?1
+        ORDER BY record_id;",
+    )?;
+    let update_query = "
+        UPDATE records
+        SET balance_before = ?1,
+            balance_after = ?2
+        WHERE record_id = ?3;";
+    for (budget_id, initial_funds, current_funds, initial_changed) in budgets {
+        let records = record_stmt
+            .query_map([budget_id], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    (row.get::<_, String>(1)?, row.get::<_, f64>(2)?),
+                ))
+            })?
+            .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+        let (ids, actions): (Vec<u32>, Vec<(String, f64)>) = records.into_iter().unzip();
+        let opening = if initial_changed {
+            None
+        } else {
+            Some(initial_funds)
+        };
+        let balances = compute_balances(opening, current_funds, &actions);
+        for (record_id, (before, after)) in ids.iter().zip(balances) {
+            db.execute(update_query, (before, after, record_id))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn add_pending_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        ALTER TABLE records ADD COLUMN pending INTEGER NOT NULL DEFAULT 0;";
+    db.execute(query, ())?;
+    Ok(())
+}
+
+pub fn add_rate_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute("ALTER TABLE records ADD COLUMN rate REAL;", ())?;
+    Ok(())
+}
+
+pub fn add_expression_column(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute("ALTER TABLE records ADD COLUMN expression TEXT;", ())?;
+    Ok(())
+}
+
 pub fn insert_record(db: &Connection, record: &Record) -> Result<usize, rusqlite::Error> {
     let query = "
-        INSERT INTO records (budget_id, action, amount, description, created_at)
-        VALUES (?1, ?2, ?3, ?4, ?5);";
+        INSERT INTO records (budget_id, action, amount, description, created_at, balance_before, balance_after, pending, rate, expression, split_id, account_id, status, payee_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14);";
     db.execute(
         query,
         (
@@ -79,34 +381,154 @@ pub fn insert_record(db: &Connection, record: &Record) -> Result<usize, rusqlite
             &record.amount,
             &record.desc,
             &record.created_at,
+            &record.balance_before,
+            &record.balance_after,
+            &record.pending,
+            &record.rate,
+            &record.expression,
+            &record.split_id,
+            &record.account_id,
+            record.cleared.value(),
+            &record.payee_id,
         ),
     )
 }
 
+pub fn update_record(db: &Connection, record: &Record) -> Result<usize, rusqlite::Error> {
+    let query = "
+        UPDATE records
+        SET amount = ?1,
+            balance_before = ?2,
+            balance_after = ?3,
+            pending = ?4,
+            description = ?5,
+            created_at = ?6
+        WHERE record_id = ?7;";
+    db.execute(
+        query,
+        (
+            &record.amount,
+            &record.balance_before,
+            &record.balance_after,
+            &record.pending,
+            &record.desc,
+            &record.created_at,
+            &record.record_id,
+        ),
+    )
+}
+
+pub fn delete_record_by_id(db: &Connection, id: &u32) -> Result<usize, rusqlite::Error> {
+    db.execute("DELETE FROM records WHERE record_id = ?1;", [id])
+}
+
+pub fn select_record_by_id(db: &Connection, id: &u32) -> Result<Record, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.record_id = ?1;",
+        RECORD_QUERY
+    );
+    db.query_row(&query, [id], map_record)
+}
+
+pub fn get_records_by_split(
+    db: &Connection,
+    split_id: &u32,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.split_id = ?1
+        ORDER BY t.record_id ASC;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map([split_id], map_record)?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
+pub fn get_settled_records_after(
+    db: &Connection,
+    record: &Record,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.budget_id = ?1
+            AND t.pending = 0
+            AND (t.created_at > ?2 OR (t.created_at = ?2 AND t.record_id > ?3))
+        ORDER BY t.created_at ASC, t.record_id ASC;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map(
+        (&record.budget_id, &record.created_at, &record.record_id),
+        map_record,
+    )?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
+pub fn get_balance_before_record(
+    db: &Connection,
+    record: &Record,
+) -> Result<Option<f64>, rusqlite::Error> {
+    let query = "
+        SELECT balance_after
+        FROM records
+        WHERE budget_id = ?1
+            AND pending = 0
+            AND (created_at < ?2 OR (created_at = ?2 AND record_id < ?3))
+        ORDER BY created_at DESC, record_id DESC
+        LIMIT 1;";
+    let balance = db
+        .query_row(
+            query,
+            (&record.budget_id, &record.created_at, &record.record_id),
+            |row| row.get::<_, Option<f64>>(0),
+        )
+        .optional()?;
+    Ok(balance.flatten())
+}
+
+pub fn get_due_pending_records(
+    db: &Connection,
+    now: &DateTime<Utc>,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.pending = 1 AND t.created_at <= ?1
+        ORDER BY t.created_at ASC, t.record_id ASC;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map([now], map_record)?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
 pub fn get_records_by_budget(
     db: &Connection,
     budget_id: &u32,
+    limit: &u32,
 ) -> Result<Vec<Record>, rusqlite::Error> {
-    let query = "
-        SELECT t.record_id, b.budget_id, b.name, t.action, t.amount,  t.description, t.created_at
-        FROM records t
-        JOIN budgets b 
-        ON t.budget_id = b.budget_id
+    let query = format!(
+        "{}
         WHERE t.budget_id = ?1
-        ORDER BY t.created_at DESC
-        LIMIT 30;";
-    let mut stmt = db.prepare(query)?;
-    let record_iter = stmt.query_map([budget_id], |row| {
-        Ok(Record {
-            record_id: row.get(0)?,
-            budget_id: row.get(1)?,
-            budget_name: row.get(2)?,
-            action: row.get(3)?,
-            amount: row.get(4)?,
-            desc: row.get(5)?,
-            created_at: row.get(6)?,
-        })
-    })?;
+        ORDER BY t.created_at DESC, t.record_id DESC
+        LIMIT ?2;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map([budget_id, limit], map_record)?;
     let mut records_list = Vec::new();
     for record in record_iter {
         records_list.push(record?);
@@ -114,29 +536,164 @@ pub fn get_records_by_budget(
     Ok(records_list)
 }
 
-pub fn get_all_records(db: &Connection) -> Result<Vec<Record>, rusqlite::Error> {
-    let query = "
-        SELECT t.record_id, b.budget_id, b.name, t.action, t.amount,  t.description, t.created_at
-        FROM records t
-        JOIN budgets b 
-        ON t.budget_id = b.budget_id
-        ORDER BY t.created_at DESC
-        LIMIT 30;";
-    let mut stmt = db.prepare(query)?;
-    let record_iter = stmt.query_map([], |row| {
-        Ok(Record {
-            record_id: row.get(0)?,
-            budget_id: row.get(1)?,
-            budget_name: row.get(2)?,
-            action: row.get(3)?,
-            amount: row.get(4)?,
-            desc: row.get(5)?,
-            created_at: row.get(6)?,
-        })
-    })?;
+pub fn get_unreconciled_records(
+    db: &Connection,
+    budget_id: &u32,
+    until: &DateTime<Utc>,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.budget_id = ?1 AND t.created_at <= ?2 AND t.pending = 0
+            AND t.status != 'reconciled'
+        ORDER BY t.created_at ASC, t.record_id ASC;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map((budget_id, until), map_record)?;
     let mut records_list = Vec::new();
     for record in record_iter {
         records_list.push(record?);
     }
     Ok(records_list)
 }
+
+pub fn get_unreconciled_account_records(
+    db: &Connection,
+    account_id: &u32,
+    until: &DateTime<Utc>,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        WHERE t.account_id = ?1 AND t.created_at <= ?2 AND t.pending = 0
+            AND t.status != 'reconciled'
+        ORDER BY t.created_at ASC, t.record_id ASC;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map((account_id, until), map_record)?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
+pub fn get_records_by_subtree(
+    db: &Connection,
+    budget_id: &u32,
+    limit: &u32,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "
+        WITH RECURSIVE subtree(budget_id) AS (
+            SELECT ?1
+            UNION
+            SELECT b.budget_id
+            FROM budgets b
+            JOIN subtree s
+            ON b.parent_id = s.budget_id
+        ){}
+        WHERE t.budget_id IN subtree
+        ORDER BY t.created_at DESC, t.record_id DESC
+        LIMIT ?2;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map([budget_id, limit], map_record)?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
+pub fn get_all_records(db: &Connection, limit: &u32) -> Result<Vec<Record>, rusqlite::Error> {
+    let query = format!(
+        "{}
+        ORDER BY t.created_at DESC, t.record_id DESC
+        LIMIT ?1;",
+        RECORD_QUERY
+    );
+    let mut stmt = db.prepare(&query)?;
+    let record_iter = stmt.query_map([limit], map_record)?;
+    let mut records_list = Vec::new();
+    for record in record_iter {
+        records_list.push(record?);
+    }
+    Ok(records_list)
+}
+
+const RECORD_QUERY: &str = "
+        SELECT t.record_id, b.budget_id, b.name, t.action, t.amount, t.description, t.created_at,
+            t.balance_before, t.balance_after, t.pending, t.rate, b.currency,
+            t.expression, t.split_id, t.account_id, a.name, t.status, t.payee_id, p.name
+        FROM records t
+        JOIN budgets b
+        ON t.budget_id = b.budget_id
+        LEFT JOIN accounts a
+        ON t.account_id = a.account_id
+        LEFT JOIN payees p
+        ON t.payee_id = p.payee_id";
+
+fn map_record(row: &rusqlite::Row) -> Result<Record, rusqlite::Error> {
+    Ok(Record {
+        record_id: row.get(0)?,
+        budget_id: row.get(1)?,
+        budget_name: row.get(2)?,
+        action: row.get(3)?,
+        amount: row.get(4)?,
+        desc: row.get(5)?,
+        created_at: row.get(6)?,
+        balance_before: row.get(7)?,
+        balance_after: row.get(8)?,
+        pending: row.get(9)?,
+        rate: row.get(10)?,
+        currency: row.get(11)?,
+        expression: row.get(12)?,
+        split_id: row.get(13)?,
+        account_id: row.get(14)?,
+        account_name: row.get(15)?,
+        cleared: ClearStatus::from_value(&row.get::<_, String>(16)?),
+        payee_id: row.get(17)?,
+        payee_name: row.get(18)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions(list: &[(&str, f64)]) -> Vec<(String, f64)> {
+        list.iter()
+            .map(|(action, amount)| (action.to_string(), *amount))
+            .collect()
+    }
+
+    #[test]
+    fn compute_balances_ok() {
+        let list = actions(&[("Reduce funds", 20.0), ("Increase funds", 5.0)]);
+        let balances = compute_balances(Some(100.0), 85.0, &list);
+        assert_eq!(balances[0], (Some(100.0), Some(80.0)));
+        assert_eq!(balances[1], (Some(80.0), Some(85.0)));
+    }
+
+    #[test]
+    fn compute_balances_set_ok() {
+        let list = actions(&[
+            ("Reduce funds", 20.0),
+            ("Set current funds", 90.0),
+            ("Reduce funds", 20.0),
+        ]);
+        let balances = compute_balances(Some(100.0), 70.0, &list);
+        assert_eq!(balances[1], (Some(80.0), Some(90.0)));
+        assert_eq!(balances[2], (Some(90.0), Some(70.0)));
+    }
+
+    #[test]
+    fn compute_balances_unknown_opening_ko() {
+        let list = actions(&[("Reduce funds", 10.0), ("Reset funds", 50.0)]);
+        let balances = compute_balances(None, 50.0, &list);
+        assert_eq!(balances[0], (None, None));
+        assert_eq!(balances[1], (None, Some(50.0)));
+    }
+}
@@ -0,0 +1,523 @@
+use crate::budget::Budget;
+use crate::chart::Period;
+use crate::color::{paint, Role};
+use crate::config::Config;
+use crate::money::MoneyFormat;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::record::{reduction_actions, INCREASES, REDUCTIONS};
+use crate::table::{Column, Table};
+use crate::timeline::BudgetState;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use clap::ValueEnum;
+use rusqlite::Connection;
+
+pub struct ActionTotal {
+    pub budget_id: u32,
+    pub action: String,
+    pub count: u32,
+    pub total: f64,
+}
+
+pub struct MonthlyRow {
+    pub budget_id: u32,
+    pub name: Option<String>,
+    pub currency: Option<String>,
+    pub opening: Option<f64>,
+    pub increases: f64,
+    pub reductions: f64,
+    pub resets: u32,
+    pub closing: Option<f64>,
+}
+
+pub struct VarianceRow {
+    pub budget_id: u32,
+    pub name: String,
+    pub currency: Option<String>,
+    pub current: f64,
+    pub baseline: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Comparison {
+    Previous,
+    YearAgo,
+}
+
+impl Comparison {
+    pub fn baseline(&self, period: &Period, start: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Previous => period.previous(start),
+            Self::YearAgo => period.year_before(start),
+        }
+    }
+
+    pub fn baseline_range(
+        &self,
+        period: &Period,
+        start: NaiveDate,
+        today: NaiveDate,
+    ) -> (NaiveDate, NaiveDate) {
+        let baseline = self.baseline(period, start);
+        let elapsed = Days::new((today - start).num_days().max(0) as u64 + 1);
+        let end = (baseline + elapsed).min(period.following(baseline));
+        (baseline, end)
+    }
+}
+
+impl VarianceRow {
+    pub fn change(&self) -> f64 {
+        self.current - self.baseline
+    }
+
+    pub fn percent(&self) -> Option<f64> {
+        (self.baseline != 0.0).then(|| self.change() / self.baseline * 100.0)
+    }
+}
+
+pub struct DescriptionTotal {
+    pub budget_id: u32,
+    pub description: String,
+    pub count: u32,
+    pub total: f64,
+}
+
+pub fn get_action_totals(
+    db: &Connection,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+) -> Result<Vec<ActionTotal>, rusqlite::Error> {
+    let query = "
+        SELECT budget_id, action, COUNT(*), SUM(amount)
+        FROM records
+        WHERE pending = 0 AND created_at >= ?1 AND created_at < ?2
+        GROUP BY budget_id, action
+        ORDER BY budget_id, action;";
+    let mut stmt = db.prepare(query)?;
+    let totals = stmt
+        .query_map((from, to), |row| {
+            Ok(ActionTotal {
+                budget_id: row.get(0)?,
+                action: row.get(1)?,
+                count: row.get(2)?,
+                total: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<ActionTotal>, rusqlite::Error>>()?;
+    Ok(totals)
+}
+
+pub fn get_top_descriptions(
+    db: &Connection,
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+    limit: &u32,
+) -> Result<Vec<DescriptionTotal>, rusqlite::Error> {
+    let query = format!(
+        "
+        SELECT budget_id, description, count, total
+        FROM (
+            SELECT budget_id, description, COUNT(*) AS count, SUM(amount) AS total,
+                ROW_NUMBER() OVER (
+                    PARTITION BY budget_id ORDER BY SUM(amount) DESC, description
+                ) AS position
+            FROM records
+            WHERE action IN {} AND pending = 0
+                AND description IS NOT NULL AND description <> ''
+                AND created_at >= ?1 AND created_at < ?2
+            GROUP BY budget_id, description
+        )
+        WHERE position <= ?3
+        ORDER BY budget_id, position;",
+        reduction_actions()
+    );
+    let mut stmt = db.prepare(&query)?;
+    let totals = stmt
+        .query_map((from, to, limit), |row| {
+            Ok(DescriptionTotal {
+                budget_id: row.get(0)?,
+                description: row.get(1)?,
+                count: row.get(2)?,
+                total: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<DescriptionTotal>, rusqlite::Error>>()?;
+    Ok(totals)
+}
+
+pub fn build_monthly_report(
+    opening: &[BudgetState],
+    closing: &[BudgetState],
+    totals: &[ActionTotal],
+) -> Vec<MonthlyRow> {
+    let mut ids: Vec<u32> = opening
+        .iter()
+        .chain(closing)
+        .map(|state| state.budget_id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids.iter()
+        .map(|id| {
+            let open = opening.iter().find(|state| state.budget_id == *id);
+            let close = closing.iter().find(|state| state.budget_id == *id);
+            let latest = close.or(open).unwrap();
+            let sum = |actions: &[&str]| {
+                totals
+                    .iter()
+                    .filter(|total| {
+                        total.budget_id == *id && actions.contains(&total.action.as_str())
+                    })
+                    .fold(0.0, |sum, total| sum + total.total)
+            };
+            MonthlyRow {
+                budget_id: *id,
+                name: latest.name.clone(),
+                currency: latest.currency.clone(),
+                opening: open.and_then(|state| state.current_funds),
+                increases: sum(&INCREASES),
+                reductions: sum(&REDUCTIONS),
+                resets: totals
+                    .iter()
+                    .filter(|total| total.budget_id == *id && total.action == "Reset funds")
+                    .map(|total| total.count)
+                    .sum(),
+                closing: close.and_then(|state| state.current_funds),
+            }
+        })
+        .collect()
+}
+
+pub fn build_variance_report(
+    budgets: &[Budget],
+    current: &[ActionTotal],
+    baseline: &[ActionTotal],
+) -> Vec<VarianceRow> {
+    let spent = |totals: &[ActionTotal], id: u32| {
+        totals
+            .iter()
+            .filter(|total| total.budget_id == id && REDUCTIONS.contains(&total.action.as_str()))
+            .fold(0.0, |sum, total| sum + total.total)
+    };
+    let mut rows: Vec<VarianceRow> = budgets
+        .iter()
+        .filter_map(|budget| {
+            let id = budget.budget_id?;
+            Some(VarianceRow {
+                budget_id: id,
+                name: budget.name.to_owned(),
+                currency: budget.currency.clone(),
+                current: spent(current, id),
+                baseline: spent(baseline, id),
+            })
+        })
+        .collect();
+    rows.sort_by(|a, b| b.change().abs().total_cmp(&a.change().abs()));
+    rows
+}
+
+pub fn print_monthly_report(
+    rows: &[MonthlyRow],
+    descriptions: &[DescriptionTotal],
+    month: &str,
+    config: &Config,
+) {
+    let top = |id: u32| descriptions.iter().filter(move |top| top.budget_id == id);
+    if config.output != OutputFormat::Table {
+        let cells: Vec<Vec<Cell>> = rows
+            .iter()
+            .map(|row| {
+                let money = config.money_format(&row.currency);
+                let descriptions: Vec<String> = top(row.budget_id)
+                    .map(|top| format!("{} ({})", top.description, money.format(top.total)))
+                    .collect();
+                vec![
+                    Cell::text(month),
+                    Cell::Number(row.budget_id.into()),
+                    Cell::optional_text(&row.name),
+                    Cell::Text(config.currency_code(&row.currency)),
+                    Cell::optional_number(row.opening),
+                    Cell::Number(row.increases),
+                    Cell::Number(row.reductions),
+                    Cell::Number(row.resets.into()),
+                    Cell::optional_number(row.closing),
+                    Cell::Text(descriptions.join("; ")),
+                ]
+            })
+            .collect();
+        let headers = [
+            "month",
+            "id",
+            "budget",
+            "currency",
+            "opening",
+            "increases",
+            "reductions",
+            "resets",
+            "closing",
+            "top_descriptions",
+        ];
+        return print_rows(&config.output, &headers, &cells);
+    }
+    println!("\nMonthly report for {}", month);
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::right("OPENING"),
+        Column::right("INCREASES"),
+        Column::right("REDUCTIONS"),
+        Column::right("RESETS"),
+        Column::right("CLOSING"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    let format_balance = |balance: Option<f64>, money: &MoneyFormat| match balance {
+        Some(balance) => money.format(balance),
+        None => String::from("-"),
+    };
+    for row in rows {
+        let money = config.money_format(&row.currency);
+        table.add_row(vec![
+            row.budget_id.to_string(),
+            row.name.clone().unwrap_or_default(),
+            format_balance(row.opening, &money),
+            money.format(row.increases),
+            money.format(row.reductions),
+            row.resets.to_string(),
+            format_balance(row.closing, &money),
+        ]);
+        table.style_cell(3, config.style(Role::Positive));
+        table.style_cell(4, config.style(Role::Negative));
+        table.style_cell(6, config.amount_style(row.closing.unwrap_or_default()));
+    }
+    table.print();
+    if descriptions.is_empty() {
+        return;
+    }
+    let mut table = Table::new(vec![
+        Column::left("BUDGET"),
+        Column::left("DESCRIPTION"),
+        Column::right("COUNT"),
+        Column::right("TOTAL"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for row in rows {
+        let money = config.money_format(&row.currency);
+        for top in top(row.budget_id) {
+            table.add_row(vec![
+                row.name.clone().unwrap_or_default(),
+                top.description.to_owned(),
+                top.count.to_string(),
+                money.format(top.total),
+            ]);
+        }
+    }
+    table.print();
+}
+
+pub fn print_variance_report(rows: &[VarianceRow], current: &str, baseline: &str, config: &Config) {
+    if config.output != OutputFormat::Table {
+        let cells: Vec<Vec<Cell>> = rows
+            .iter()
+            .map(|row| {
+                vec![
+                    Cell::Number(row.budget_id.into()),
+                    Cell::text(&row.name),
+                    Cell::Text(config.currency_code(&row.currency)),
+                    Cell::text(current),
+                    Cell::Number(row.current),
+                    Cell::text(baseline),
+                    Cell::Number(row.baseline),
+                    Cell::Number(row.change()),
+                    Cell::optional_number(row.percent()),
+                ]
+            })
+            .collect();
+        let headers = [
+            "id",
+            "budget",
+            "currency",
+            "period",
+            "spent",
+            "baseline_period",
+            "baseline_spent",
+            "change",
+            "percent_change",
+        ];
+        return print_rows(&config.output, &headers, &cells);
+    }
+    println!("\nSpending in {} compared with {}", current, baseline);
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::right(&current.to_uppercase()),
+        Column::right(&baseline.to_uppercase()),
+        Column::right("CHANGE"),
+        Column::right("%"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for row in rows {
+        let money = config.money_format(&row.currency);
+        let sign = if row.change() > 0.0 { "+" } else { "" };
+        let percent = match row.percent() {
+            Some(percent) => format!("{}{:.0}%", sign, percent),
+            None => String::from("-"),
+        };
+        let role = match row.change() {
+            change if change > 0.0 => Some(Role::Negative),
+            change if change < 0.0 => Some(Role::Positive),
+            _ => None,
+        };
+        table.add_row(vec![
+            row.budget_id.to_string(),
+            row.name.to_owned(),
+            money.format(row.current),
+            money.format(row.baseline),
+            format!("{}{}", sign, money.format(row.change())),
+            percent,
+        ]);
+        let style = role.and_then(|role| config.style(role));
+        table.style_cell(4, style);
+        table.style_cell(5, style);
+    }
+    table.print();
+    let largest = |increase: bool| {
+        rows.iter()
+            .filter(|row| (row.change() > 0.0) == increase && row.change() != 0.0)
+            .max_by(|a, b| a.change().abs().total_cmp(&b.change().abs()))
+    };
+    for (label, row) in [("increase", largest(true)), ("decrease", largest(false))] {
+        if let Some(row) = row {
+            let money = config.money_format(&row.currency);
+            let swing = format!(
+                "Largest {}: {} {}",
+                label,
+                row.name,
+                money.format(row.change().abs())
+            );
+            println!("{}", paint(&swing, config.style(Role::Header)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::insert_budget;
+    use crate::database::migrate;
+    use chrono::Duration;
+
+    fn state(id: u32, funds: f64) -> BudgetState {
+        BudgetState {
+            budget_id: id,
+            name: Some(String::from("Food")),
+            initial_funds: Some(100.0),
+            current_funds: Some(funds),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn get_top_descriptions_ok() {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        insert_budget(&db, &Budget::new("Food", &100.0)).unwrap();
+        let now = Utc::now();
+        db.execute(
+            "INSERT INTO records (budget_id, action, amount, description, created_at, pending)
+            VALUES (1, 'Reduce funds', 10.0, 'market', ?1, 0),
+            (1, 'Reduce funds', 15.0, 'market', ?1, 0),
+            (1, 'Reduce funds', 20.0, 'bakery', ?1, 0),
+            (1, 'Reduce funds', 1.0, 'kiosk', ?1, 0),
+            (1, 'Increase funds', 50.0, 'salary', ?1, 0);",
+            [now],
+        )
+        .unwrap();
+        let from = now - Duration::try_hours(1).unwrap();
+        let to = now + Duration::try_hours(1).unwrap();
+        let top = get_top_descriptions(&db, &from, &to, &2).unwrap();
+        let names: Vec<&str> = top.iter().map(|top| top.description.as_str()).collect();
+        assert_eq!(names, ["market", "bakery"]);
+        assert_eq!((top[0].count, top[0].total), (2, 25.0));
+        let totals = get_action_totals(&db, &from, &to).unwrap();
+        assert_eq!(totals.len(), 2);
+    }
+
+    #[test]
+    fn build_monthly_report_ok() {
+        let totals = vec![
+            ActionTotal {
+                budget_id: 1,
+                action: String::from("Reduce funds"),
+                count: 2,
+                total: 30.0,
+            },
+            ActionTotal {
+                budget_id: 1,
+                action: String::from("Transfer in"),
+                count: 1,
+                total: 5.0,
+            },
+            ActionTotal {
+                budget_id: 1,
+                action: String::from("Reset funds"),
+                count: 1,
+                total: 100.0,
+            },
+        ];
+        let rows = build_monthly_report(
+            &[state(1, 80.0)],
+            &[state(1, 75.0), state(2, 10.0)],
+            &totals,
+        );
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].opening, Some(80.0));
+        assert_eq!(
+            (rows[0].increases, rows[0].reductions, rows[0].resets),
+            (5.0, 30.0, 1)
+        );
+        assert_eq!(rows[0].closing, Some(75.0));
+        assert_eq!(rows[1].opening, None);
+    }
+
+    #[test]
+    fn build_variance_report_ok() {
+        let total = |budget_id: u32, total: f64| ActionTotal {
+            budget_id,
+            action: String::from("Reduce funds"),
+            count: 1,
+            total,
+        };
+        let mut food = Budget::new("Food", &100.0);
+        food.budget_id = Some(1);
+        let mut rent = Budget::new("Rent", &900.0);
+        rent.budget_id = Some(2);
+        let mut transfer = total(1, 10.0);
+        transfer.action = String::from("Transfer out");
+        let rows = build_variance_report(
+            &[food, rent],
+            &[total(1, 50.0), transfer, total(2, 800.0)],
+            &[total(1, 40.0)],
+        );
+        assert_eq!(rows[0].name, "Rent");
+        assert_eq!(rows[0].percent(), None);
+        assert_eq!(rows[1].change(), 20.0);
+        assert_eq!(rows[1].percent(), Some(50.0));
+    }
+
+    #[test]
+    fn baseline_range_ok() {
+        let date = |month: u32, day: u32| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+        let previous = Comparison::Previous;
+        assert_eq!(
+            previous.baseline_range(&Period::Month, date(3, 1), date(3, 10)),
+            (date(2, 1), date(2, 11))
+        );
+        assert_eq!(
+            previous.baseline_range(&Period::Month, date(3, 1), date(3, 31)),
+            (date(2, 1), date(3, 1))
+        );
+        assert_eq!(
+            Comparison::YearAgo.baseline_range(&Period::Week, date(3, 2), date(3, 3)),
+            (date(3, 2) - Days::new(364), date(3, 4) - Days::new(364))
+        );
+    }
+}
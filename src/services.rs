@@ -1,113 +1,2163 @@
+use crate::account::{
+    account_effect, build_allocations, count_account_entries, delete_account_by_id, insert_account,
+    select_account_by_id, select_all_accounts, update_account_balance, Account, AccountKind,
+    Allocation,
+};
+use crate::audit::{
+    get_all_audit_entries, get_audit_entries_by_budget, insert_audit_entry, AuditEntry,
+};
+use crate::backup::{backup_db, default_backup_path, restore_db, snapshot_db};
 use crate::budget::{
-    delete_budget_by_id, insert_budget, select_all_budgets, select_budget_by_id, update_budget,
-    Budget,
+    delete_budget_by_id, insert_budget, select_all_budgets, select_budget_by_id, sort_budgets,
+    update_budget, Budget, SortKey, Status,
 };
+use crate::chart::{get_balance_trends, get_spending_by_period, Period, PeriodTotal};
 use crate::cli::Command;
-use crate::record::{get_all_records, get_records_by_budget, insert_record, Record};
+use crate::dates::{date_range, month_range, today};
+use crate::error::Error;
+use crate::expression::Expression;
+use crate::forecast::{burn_rate, get_daily_reductions, project, Forecast, Weighting};
+use crate::goal::{get_contributions, track_goal, Goal, GoalProgress};
+use crate::hierarchy::{arrange, descendant_ids, exceeded_cap, get_reductions, roll_up};
+use crate::income::{get_unassigned_income, insert_income};
+use crate::journal::{
+    get_operation_budgets, redo_operations, undo_operations, Operation, OperationSummary,
+    SETTLE_ACTION,
+};
+use crate::payee::{
+    find_payee, get_payee_totals, insert_payee, insert_payee_alias, normalize_payee, payee_name,
+    select_all_payees, Payee, PayeeTotal,
+};
+use crate::rates::{
+    find_rate, get_all_rates, get_rate_row_id, parse_rates_csv, upsert_rate, ExchangeRate,
+};
+use crate::reconcile::{
+    get_account_change_after, get_change_after, last_reconciled_account_at, last_reconciled_at,
+    set_record_status, ClearStatus, Reconciliation,
+};
+use crate::record::{
+    apply_action, delete_record_by_id, get_all_records, get_balance_before_record,
+    get_due_pending_records, get_records_by_budget, get_records_by_split, get_records_by_subtree,
+    get_settled_records_after, get_unreconciled_account_records, get_unreconciled_records,
+    insert_record, select_record_by_id, update_record, Record,
+};
+use crate::report::{
+    build_monthly_report, build_variance_report, get_action_totals, get_top_descriptions,
+    Comparison, DescriptionTotal, MonthlyRow, VarianceRow,
+};
+use crate::split::{delete_split_by_id, insert_split, select_split_by_id, update_split, Split};
+use crate::timeline::{get_budget_states_at, BudgetState};
+use crate::utils::matches_pattern;
+use chrono::{DateTime, Days, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
 use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TREND_POINTS: usize = 12;
 
-pub fn create_budget(db: &Connection, name: &str, funds: &f64) -> Result<usize, rusqlite::Error> {
-    let budget = Budget::new(name, funds);
-    insert_budget(db, &budget)
+pub fn create_budget(
+    db: &Connection,
+    name: &str,
+    funds: &f64,
+    currency: &str,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    let mut budget = Budget::new(name, funds);
+    budget.currency = Some(currency.to_string());
+    let rows = insert_budget(&tx, &budget)?;
+    let id = tx.last_insert_rowid();
+    operation.track_new("budgets", id)?;
+    let entry = AuditEntry::new(
+        &(id as u32),
+        command.value(),
+        "name",
+        None,
+        Some(budget.name),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
 }
 
-pub fn remove_budget(db: &Connection, id: &u32) -> Result<usize, rusqlite::Error> {
-    delete_budget_by_id(db, id)
+pub fn remove_budget(
+    db: &Connection,
+    id: &u32,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let budgets = select_budget_by_id(&tx, id)?;
+    let rows = delete_budget_by_id(&tx, id)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "name",
+        Some(budgets[0].name.to_owned()),
+        None,
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
 }
 
-pub fn rename_budget(db: &Connection, id: &u32, name: &str) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
+pub fn rename_budget(
+    db: &Connection,
+    id: &u32,
+    name: &str,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let mut budgets = select_budget_by_id(&tx, id)?;
     let budget = &mut budgets[0];
+    let old_name = budget.name.to_owned();
     budget.rename(name);
-    update_budget(db, budget)
+    let rows = update_budget(&tx, budget)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "name",
+        Some(old_name),
+        Some(budget.name.to_owned()),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
 }
 
-pub fn increase_funds(
+pub fn create_account(
+    db: &Connection,
+    name: &str,
+    kind: &AccountKind,
+    balance: &f64,
+    currency: &str,
+    command: &Command,
+) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    let mut account = Account::new(name, kind, balance);
+    account.currency = Some(currency.to_string());
+    let rows = insert_account(&tx, &account)?;
+    operation.track_new("accounts", tx.last_insert_rowid())?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn remove_account(db: &Connection, id: &u32, command: &Command) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    let entries = count_account_entries(&tx, id)?;
+    if entries > 0 {
+        return Err(Error::Invalid(format!(
+            "account {} still has {} records or income entries, remove them first",
+            id, entries
+        )));
+    }
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    operation.track("accounts", *id as i64)?;
+    let rows = delete_account_by_id(&tx, id)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn get_accounts(
+    db: &Connection,
+    base: &str,
+) -> Result<(Vec<Account>, Vec<Allocation>), rusqlite::Error> {
+    let accounts = select_all_accounts(db)?;
+    let budgets = select_all_budgets(db)?;
+    let unassigned = get_unassigned_income(db)?.unwrap_or_default();
+    let allocations = build_allocations(&accounts, &budgets, unassigned, base);
+    Ok((accounts, allocations))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn change_funds(
     db: &Connection,
     id: &u32,
     amount: &f64,
+    expression: &Option<Expression>,
     command: &Command,
     description: &Option<String>,
-) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
+    date: &Option<DateTime<Utc>>,
+    account: &Option<u32>,
+    payee: &Option<String>,
+) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    check_unlocked(&tx, id, &date.unwrap_or(Utc::now()))?;
+    check_account_unlocked(&tx, account, &date.unwrap_or(Utc::now()))?;
+    let mut budgets = select_budget_by_id(&tx, id)?;
+    let amount = &resolve_amount(&budgets[0], amount, expression)?;
+    if command.value() == "Reduce funds" {
+        check_spending_caps(&tx, &[(*id, *amount)])?;
+    }
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    let mut transaction = Record::new(id, command.value(), amount, description);
+    transaction.amount = apply_record_action(&mut budgets[0], &transaction.action, amount);
+    transaction.expression = expression
+        .as_ref()
+        .map(|expression| expression.text.to_owned());
+    transaction.account_id = *account;
+    transaction.payee_id = resolve_payee(&tx, &mut operation, payee)?;
+    if let Some(date) = date {
+        transaction.created_at = *date;
+    }
+    transaction.pending = transaction.created_at > Utc::now();
+    let rows = insert_record(&tx, &transaction)?;
+    transaction.record_id = Some(tx.last_insert_rowid() as u32);
+    operation.track_new("records", tx.last_insert_rowid())?;
+    if !transaction.pending {
+        post_record(&tx, &mut operation, &mut transaction)?;
+    }
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+fn resolve_amount(
+    budget: &Budget,
+    amount: &f64,
+    expression: &Option<Expression>,
+) -> Result<f64, Error> {
+    match expression {
+        Some(expression) => expression
+            .evaluate(Some(budget.current_funds))
+            .map_err(Error::Invalid),
+        None => Ok(*amount),
+    }
+}
+
+fn apply_record_action(budget: &mut Budget, action: &str, amount: &f64) -> f64 {
+    match action {
+        "Increase funds" | "Transfer in" | "Assign funds" => budget.increase_funds(amount),
+        "Reduce funds" | "Transfer out" => budget.reduce_funds(amount),
+        "Set current funds" => budget.set_current_funds(amount),
+        "Set initial funds" => budget.set_initial_funds(amount),
+        "Reset funds" => {
+            budget.reset_funds();
+            return budget.initial_funds;
+        }
+        _ => (),
+    }
+    *amount
+}
+
+fn post_record(
+    db: &Connection,
+    operation: &mut Operation,
+    record: &mut Record,
+) -> Result<(), rusqlite::Error> {
+    let mut budgets = select_budget_by_id(db, &record.budget_id)?;
     let budget = &mut budgets[0];
-    budget.increase_funds(amount);
-    let rows = update_budget(db, budget)?;
-    let transaction = Record::new(id, command.value(), amount, description);
-    insert_record(db, &transaction)?;
+    let later_records = get_settled_records_after(db, record)?;
+    let balance_before = match later_records.first() {
+        None => budget.current_funds,
+        Some(next) => match next.balance_before {
+            Some(balance) => balance,
+            None => get_balance_before_record(db, record)?.unwrap_or(budget.current_funds),
+        },
+    };
+    let mut state = budget.clone();
+    state.current_funds = balance_before;
+    record.amount = apply_record_action(&mut state, &record.action, &record.amount);
+    record.balance_before = Some(balance_before);
+    record.balance_after = Some(state.current_funds);
+    record.pending = false;
+    operation.track("budgets", record.budget_id as i64)?;
+    operation.track("records", record.record_id.unwrap_or_default() as i64)?;
+    update_record(db, record)?;
+    if let Some(account_id) = record.account_id {
+        operation.track("accounts", account_id as i64)?;
+        update_account_balance(
+            db,
+            &account_id,
+            &account_effect(&record.action, &record.amount),
+        )?;
+    }
+    let initial_changed_later = later_records
+        .iter()
+        .any(|later| later.action == "Set initial funds");
+    let mut running = state.current_funds;
+    for mut later in later_records {
+        operation.track("records", later.record_id.unwrap_or_default() as i64)?;
+        later.balance_before = Some(running);
+        running = apply_action(&later.action, &later.amount, Some(running)).unwrap_or(running);
+        later.balance_after = Some(running);
+        update_record(db, &later)?;
+    }
+    budget.current_funds = running;
+    if !initial_changed_later {
+        budget.initial_funds = state.initial_funds;
+    }
+    update_budget(db, budget)?;
+    Ok(())
+}
+
+fn unpost_record(
+    db: &Connection,
+    operation: &mut Operation,
+    record: &Record,
+) -> Result<(), rusqlite::Error> {
+    let mut budgets = select_budget_by_id(db, &record.budget_id)?;
+    let budget = &mut budgets[0];
+    operation.track("budgets", record.budget_id as i64)?;
+    if let Some(account_id) = record.account_id {
+        operation.track("accounts", account_id as i64)?;
+        update_account_balance(
+            db,
+            &account_id,
+            &-account_effect(&record.action, &record.amount),
+        )?;
+    }
+    let mut running = record
+        .balance_before
+        .unwrap_or(budget.current_funds + record.amount);
+    for mut later in get_settled_records_after(db, record)? {
+        operation.track("records", later.record_id.unwrap_or_default() as i64)?;
+        later.balance_before = Some(running);
+        running = apply_action(&later.action, &later.amount, Some(running)).unwrap_or(running);
+        later.balance_after = Some(running);
+        update_record(db, &later)?;
+    }
+    budget.current_funds = running;
+    update_budget(db, budget)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reduce_split(
+    db: &Connection,
+    parts: &[(u32, f64, Option<Expression>)],
+    command: &Command,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+    account: &Option<u32>,
+    payee: &Option<String>,
+) -> Result<usize, Error> {
+    for (id, _, _) in parts {
+        check_account(db, id, account)?;
+    }
+    let tx = db.unchecked_transaction()?;
+    for (id, _, _) in parts {
+        check_unlocked(&tx, id, &date.unwrap_or(Utc::now()))?;
+    }
+    check_account_unlocked(&tx, account, &date.unwrap_or(Utc::now()))?;
+    let parts = parts
+        .iter()
+        .map(|(id, amount, expression)| {
+            let budget = select_budget_by_id(&tx, id)?.remove(0);
+            Ok((*id, resolve_amount(&budget, amount, expression)?))
+        })
+        .collect::<Result<Vec<(u32, f64)>, Error>>()?;
+    if parts.iter().any(|(_, amount)| *amount <= 0.0) {
+        return Err(Error::Invalid(String::from(
+            "every part of a split must be greater than zero",
+        )));
+    }
+    check_spending_caps(&tx, &parts)?;
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    let split = Split {
+        split_id: None,
+        desc: description.to_owned(),
+        created_at: date.unwrap_or(Utc::now()),
+    };
+    insert_split(&tx, &split)?;
+    let split_id = tx.last_insert_rowid();
+    operation.track_new("splits", split_id)?;
+    let payee_id = resolve_payee(&tx, &mut operation, payee)?;
+    let mut rows = 0;
+    for (id, amount) in &parts {
+        let mut transaction = Record::new(id, "Reduce funds", amount, description);
+        transaction.split_id = Some(split_id as u32);
+        transaction.account_id = *account;
+        transaction.payee_id = payee_id;
+        transaction.created_at = split.created_at;
+        transaction.pending = split.created_at > Utc::now();
+        rows += insert_record(&tx, &transaction)?;
+        transaction.record_id = Some(tx.last_insert_rowid() as u32);
+        operation.track_new("records", tx.last_insert_rowid())?;
+        if !transaction.pending {
+            post_record(&tx, &mut operation, &mut transaction)?;
+        }
+    }
+    operation.finish()?;
+    tx.commit()?;
     Ok(rows)
 }
 
-pub fn reduce_funds(
+pub fn edit_split(
     db: &Connection,
     id: &u32,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+    command: &Command,
+) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut split = select_split_by_id(&tx, id)?;
+    let records = get_records_by_split(&tx, id)?;
+    for record in &records {
+        check_unlocked(&tx, &record.budget_id, &record.created_at)?;
+        if let Some(date) = date {
+            check_unlocked(&tx, &record.budget_id, date)?;
+        }
+    }
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    operation.track("splits", *id as i64)?;
+    if description.is_some() {
+        split.desc = description.to_owned();
+    }
+    for record_id in records.iter().filter_map(|record| record.record_id) {
+        operation.track("records", record_id as i64)?;
+        let mut record = select_record_by_id(&tx, &record_id)?;
+        if description.is_some() {
+            record.desc = description.to_owned();
+        }
+        if let Some(date) = date {
+            if !record.pending {
+                unpost_record(&tx, &mut operation, &record)?;
+            }
+            record.created_at = *date;
+            record.pending = true;
+        }
+        update_record(&tx, &record)?;
+        if record.pending && record.created_at <= Utc::now() {
+            post_record(&tx, &mut operation, &mut record)?;
+        }
+    }
+    if let Some(date) = date {
+        split.created_at = *date;
+    }
+    let rows = update_split(&tx, &split)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn delete_split(db: &Connection, id: &u32, command: &Command) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    select_split_by_id(&tx, id)?;
+    let records = get_records_by_split(&tx, id)?;
+    for record in &records {
+        check_unlocked(&tx, &record.budget_id, &record.created_at)?;
+    }
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    for record_id in records.iter().filter_map(|record| record.record_id) {
+        operation.track("records", record_id as i64)?;
+        let record = select_record_by_id(&tx, &record_id)?;
+        if !record.pending {
+            unpost_record(&tx, &mut operation, &record)?;
+        }
+        delete_record_by_id(&tx, &record_id)?;
+    }
+    operation.track("splits", *id as i64)?;
+    delete_split_by_id(&tx, id)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(records.len())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_funds(
+    db: &Connection,
+    from: &u32,
+    to: &u32,
+    amount: &f64,
+    rate: &Option<f64>,
+    base: &str,
+    command: &Command,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+) -> Result<usize, Error> {
+    if from == to {
+        return Err(Error::Invalid(String::from(
+            "cannot transfer funds to the same budget",
+        )));
+    }
+    if *amount <= 0.0 {
+        return Err(Error::Invalid(String::from(
+            "amount must be greater than zero",
+        )));
+    }
+    let tx = db.unchecked_transaction()?;
+    let source = select_budget_by_id(&tx, from)?.remove(0);
+    let target = select_budget_by_id(&tx, to)?.remove(0);
+    let source_code = source.currency.clone().unwrap_or(base.to_string());
+    let target_code = target.currency.clone().unwrap_or(base.to_string());
+    let rate = match rate {
+        Some(_) if source_code == target_code => {
+            return Err(Error::Invalid(format!(
+                "both budgets use {}, a transfer between them takes no rate",
+                source_code
+            )))
+        }
+        Some(rate) if *rate <= 0.0 => {
+            return Err(Error::Invalid(String::from(
+                "the exchange rate must be greater than zero",
+            )))
+        }
+        _ if source_code == target_code => None,
+        Some(rate) => Some(*rate),
+        None => Some(
+            find_rate(&tx, &source_code, &target_code)?.ok_or(Error::Invalid(format!(
+                "no exchange rate from {} to {}, add one with 'budget rates set'",
+                source_code, target_code
+            )))?,
+        ),
+    };
+    let created_at = date.unwrap_or(Utc::now());
+    check_unlocked(&tx, from, &created_at)?;
+    check_unlocked(&tx, to, &created_at)?;
+    check_spending_caps(&tx, &[(*from, *amount)])?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(from))?;
+    let legs = [
+        (
+            from,
+            "Transfer out",
+            *amount,
+            format!("Transfer to {}", target.name),
+        ),
+        (
+            to,
+            "Transfer in",
+            amount * rate.unwrap_or(1.0),
+            format!("Transfer from {}", source.name),
+        ),
+    ];
+    let mut rows = 0;
+    for (id, action, amount, default_description) in legs {
+        let description = description.clone().or(Some(default_description));
+        let mut transaction = Record::new(id, action, &amount, &description);
+        transaction.rate = rate;
+        transaction.created_at = created_at;
+        transaction.pending = created_at > Utc::now();
+        rows += insert_record(&tx, &transaction)?;
+        transaction.record_id = Some(tx.last_insert_rowid() as u32);
+        operation.track_new("records", tx.last_insert_rowid())?;
+        if !transaction.pending {
+            post_record(&tx, &mut operation, &mut transaction)?;
+        }
+    }
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn add_income(
+    db: &Connection,
     amount: &f64,
     command: &Command,
     description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+    account: &Option<u32>,
 ) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
-    let budget = &mut budgets[0];
-    budget.reduce_funds(amount);
-    let rows = update_budget(db, budget)?;
-    let transaction = Record::new(id, command.value(), amount, description);
-    insert_record(db, &transaction)?;
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    let created_at = date.unwrap_or(Utc::now());
+    let rows = insert_income(&tx, None, amount, description, &created_at, account)?;
+    operation.track_new("income", tx.last_insert_rowid())?;
+    if let Some(account_id) = account {
+        operation.track("accounts", *account_id as i64)?;
+        update_account_balance(&tx, account_id, amount)?;
+    }
+    operation.finish()?;
+    tx.commit()?;
     Ok(rows)
 }
 
-pub fn reset_funds(
+#[allow(clippy::too_many_arguments)]
+pub fn assign_income(
     db: &Connection,
     id: &u32,
+    amount: &f64,
+    base: &str,
     command: &Command,
     description: &Option<String>,
-) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
-    let budget = &mut budgets[0];
-    budget.reset_funds();
-    let rows = update_budget(db, budget)?;
-    let transaction = Record::new(id, command.value(), &budget.initial_funds, description);
-    insert_record(db, &transaction)?;
+    date: &Option<DateTime<Utc>>,
+) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    let unassigned = get_unassigned_income(&tx)?.unwrap_or_default();
+    if *amount > unassigned {
+        return Err(Error::Invalid(format!(
+            "only {} is left to be budgeted",
+            unassigned
+        )));
+    }
+    let budget = select_budget_by_id(&tx, id)?.remove(0);
+    let code = budget.currency.clone().unwrap_or(base.to_string());
+    let rate = if code == base {
+        None
+    } else {
+        Some(find_rate(&tx, base, &code)?.ok_or(Error::Invalid(format!(
+            "no exchange rate from {} to {}, add one with 'budget rates set'",
+            base, code
+        )))?)
+    };
+    let created_at = date.unwrap_or(Utc::now());
+    check_unlocked(&tx, id, &created_at)?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    insert_income(&tx, Some(id), &-amount, description, &created_at, &None)?;
+    operation.track_new("income", tx.last_insert_rowid())?;
+    let description = description
+        .clone()
+        .or(Some(String::from("Assigned from income")));
+    let mut transaction = Record::new(
+        id,
+        command.value(),
+        &(amount * rate.unwrap_or(1.0)),
+        &description,
+    );
+    transaction.rate = rate;
+    transaction.created_at = created_at;
+    transaction.pending = created_at > Utc::now();
+    let rows = insert_record(&tx, &transaction)?;
+    transaction.record_id = Some(tx.last_insert_rowid() as u32);
+    operation.track_new("records", tx.last_insert_rowid())?;
+    if !transaction.pending {
+        post_record(&tx, &mut operation, &mut transaction)?;
+    }
+    operation.finish()?;
+    tx.commit()?;
     Ok(rows)
 }
 
-pub fn set_current_funds(
+pub fn get_unassigned(db: &Connection) -> Result<Option<f64>, rusqlite::Error> {
+    get_unassigned_income(db)
+}
+
+pub fn settle_pending_records(db: &Connection) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let records = get_due_pending_records(&tx, &Utc::now())?;
+    let count = records.len();
+    if count == 0 {
+        return Ok(count);
+    }
+    let mut operation = Operation::begin(&tx, SETTLE_ACTION, None)?;
+    for mut record in records {
+        post_record(&tx, &mut operation, &mut record)?;
+    }
+    operation.finish()?;
+    tx.commit()?;
+    Ok(count)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn increase_funds(
     db: &Connection,
     id: &u32,
     amount: &f64,
+    expression: &Option<Expression>,
     command: &Command,
     description: &Option<String>,
-) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
-    let budget = &mut budgets[0];
-    budget.set_current_funds(amount);
-    let rows = update_budget(db, budget)?;
-    let transaction = Record::new(id, command.value(), amount, description);
-    insert_record(db, &transaction)?;
+    date: &Option<DateTime<Utc>>,
+    account: &Option<u32>,
+    payee: &Option<String>,
+) -> Result<usize, Error> {
+    check_account(db, id, account)?;
+    change_funds(
+        db,
+        id,
+        amount,
+        expression,
+        command,
+        description,
+        date,
+        account,
+        payee,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reduce_funds(
+    db: &Connection,
+    id: &u32,
+    amount: &f64,
+    expression: &Option<Expression>,
+    command: &Command,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+    account: &Option<u32>,
+    payee: &Option<String>,
+) -> Result<usize, Error> {
+    check_account(db, id, account)?;
+    change_funds(
+        db,
+        id,
+        amount,
+        expression,
+        command,
+        description,
+        date,
+        account,
+        payee,
+    )
+}
+
+fn resolve_payee(
+    db: &Connection,
+    operation: &mut Operation,
+    payee: &Option<String>,
+) -> Result<Option<u32>, Error> {
+    let Some(text) = payee else {
+        return Ok(None);
+    };
+    if let Some(payee_id) = find_payee(&select_all_payees(db)?, text) {
+        return Ok(Some(payee_id));
+    }
+    let name = payee_name(text);
+    if name.is_empty() {
+        return Err(Error::Invalid(format!("invalid payee '{}'", text)));
+    }
+    insert_payee(db, &name)?;
+    let payee_id = db.last_insert_rowid();
+    operation.track_new("payees", payee_id)?;
+    Ok(Some(payee_id as u32))
+}
+
+pub fn add_payee_alias(
+    db: &Connection,
+    id: &u32,
+    alias: &str,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    let payees = select_all_payees(&tx)?;
+    let Some(payee) = payees.iter().find(|payee| payee.payee_id == *id) else {
+        return Err(Error::Invalid(format!("no payee with ID {}", id)));
+    };
+    let alias = normalize_payee(alias);
+    if alias.is_empty() {
+        return Err(Error::Invalid(String::from(
+            "alias is empty after normalising",
+        )));
+    }
+    if let Some(other) = find_payee(&payees, &alias) {
+        if other != *id || normalize_payee(&payee.name) == alias {
+            return Err(Error::Invalid(format!("'{}' already names a payee", alias)));
+        }
+    }
+    let mut operation = Operation::begin(&tx, command.value(), None)?;
+    let rows = insert_payee_alias(&tx, id, &alias)?;
+    operation.track_new("payee_aliases", tx.last_insert_rowid())?;
+    let entry = AuditEntry::unscoped(
+        command.value(),
+        "alias",
+        None,
+        Some(format!("{} ({})", alias, payee.name)),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
     Ok(rows)
 }
 
+pub fn get_payees(db: &Connection, prefix: &Option<String>) -> Result<Vec<Payee>, rusqlite::Error> {
+    let payees = select_all_payees(db)?;
+    Ok(match prefix {
+        Some(prefix) => {
+            let prefix = normalize_payee(prefix);
+            payees
+                .into_iter()
+                .filter(|payee| normalize_payee(&payee.name).starts_with(&prefix))
+                .collect()
+        }
+        None => payees,
+    })
+}
+
+pub fn get_payee_report(
+    db: &Connection,
+    from: &NaiveDate,
+    to: &NaiveDate,
+    tz: &Option<Tz>,
+) -> Result<Vec<PayeeTotal>, Error> {
+    let (start, end) = to
+        .succ_opt()
+        .and_then(|end| date_range(from, &end, tz))
+        .ok_or(Error::Invalid(String::from(
+            "period does not exist in the selected timezone",
+        )))?;
+    Ok(get_payee_totals(db, &start, &end)?)
+}
+
+fn check_unlocked(db: &Connection, id: &u32, date: &DateTime<Utc>) -> Result<(), Error> {
+    match last_reconciled_at(db, id)? {
+        Some(locked) if *date <= locked => Err(Error::Invalid(format!(
+            "records of budget {} up to {} are reconciled and locked",
+            id,
+            locked.format("%Y-%m-%d")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn check_account_unlocked(
+    db: &Connection,
+    account: &Option<u32>,
+    date: &DateTime<Utc>,
+) -> Result<(), Error> {
+    let Some(account_id) = account else {
+        return Ok(());
+    };
+    match last_reconciled_account_at(db, account_id)? {
+        Some(locked) if *date <= locked => Err(Error::Invalid(format!(
+            "records of account {} up to {} are reconciled and locked",
+            account_id,
+            locked.format("%Y-%m-%d")
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reconcile_budget(
+    db: &Connection,
+    id: &u32,
+    statement_balance: &f64,
+    as_of: &DateTime<Utc>,
+    clear: &[u32],
+    unclear: &[u32],
+    finish: &bool,
+    command: &Command,
+) -> Result<Reconciliation, Error> {
+    let tx = db.unchecked_transaction()?;
+    let budget = select_budget_by_id(&tx, id)?.remove(0);
+    let reconciliation = Reconciliation {
+        balance: budget.current_funds - get_change_after(&tx, id, as_of)?,
+        name: budget.name,
+        currency: budget.currency,
+        as_of: *as_of,
+        statement_balance: *statement_balance,
+        records: get_unreconciled_records(&tx, id, as_of)?,
+    };
+    let reconciliation = settle_reconciliation(
+        &tx,
+        reconciliation,
+        Some(id),
+        clear,
+        unclear,
+        finish,
+        command,
+    )?;
+    tx.commit()?;
+    Ok(reconciliation)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn reconcile_account(
+    db: &Connection,
+    id: &u32,
+    statement_balance: &f64,
+    as_of: &DateTime<Utc>,
+    clear: &[u32],
+    unclear: &[u32],
+    finish: &bool,
+    command: &Command,
+) -> Result<Reconciliation, Error> {
+    let tx = db.unchecked_transaction()?;
+    let account = select_account_by_id(&tx, id)?;
+    let reconciliation = Reconciliation {
+        balance: account.balance - get_account_change_after(&tx, id, as_of)?,
+        name: account.name,
+        currency: account.currency,
+        as_of: *as_of,
+        statement_balance: *statement_balance,
+        records: get_unreconciled_account_records(&tx, id, as_of)?,
+    };
+    let reconciliation =
+        settle_reconciliation(&tx, reconciliation, None, clear, unclear, finish, command)?;
+    tx.commit()?;
+    Ok(reconciliation)
+}
+
+fn settle_reconciliation(
+    db: &Connection,
+    mut reconciliation: Reconciliation,
+    budget_id: Option<&u32>,
+    clear: &[u32],
+    unclear: &[u32],
+    finish: &bool,
+    command: &Command,
+) -> Result<Reconciliation, Error> {
+    let mut operation = if clear.is_empty() && unclear.is_empty() && !finish {
+        None
+    } else {
+        Some(Operation::begin(db, command.value(), budget_id)?)
+    };
+    let changes = clear
+        .iter()
+        .map(|record_id| (record_id, ClearStatus::Cleared))
+        .chain(
+            unclear
+                .iter()
+                .map(|record_id| (record_id, ClearStatus::Uncleared)),
+        );
+    for (record_id, status) in changes {
+        let Some(record) = reconciliation
+            .records
+            .iter_mut()
+            .find(|record| record.record_id == Some(*record_id))
+        else {
+            return Err(Error::Invalid(format!(
+                "record {} is not an unreconciled record of {} up to the statement date",
+                record_id, reconciliation.name
+            )));
+        };
+        if let Some(operation) = operation.as_mut() {
+            operation.track("records", *record_id as i64)?;
+        }
+        set_record_status(db, record_id, &status)?;
+        record.cleared = status;
+    }
+    if *finish {
+        let difference = reconciliation.difference();
+        if difference.abs() >= 0.005 {
+            return Err(Error::Invalid(format!(
+                "the statement differs from the cleared balance by {:.2}",
+                difference
+            )));
+        }
+        for record in reconciliation
+            .records
+            .iter()
+            .filter(|record| record.cleared == ClearStatus::Cleared)
+        {
+            let record_id = record.record_id.unwrap_or_default();
+            if let Some(operation) = operation.as_mut() {
+                operation.track("records", record_id as i64)?;
+            }
+            set_record_status(db, &record_id, &ClearStatus::Reconciled)?;
+        }
+        reconciliation
+            .records
+            .retain(|record| record.cleared == ClearStatus::Uncleared);
+    }
+    if let Some(operation) = operation {
+        operation.finish()?;
+    }
+    Ok(reconciliation)
+}
+
+fn check_account(db: &Connection, id: &u32, account: &Option<u32>) -> Result<(), Error> {
+    let Some(account_id) = account else {
+        return Ok(());
+    };
+    let account = match select_account_by_id(db, account_id) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(Error::Invalid(format!("no account with ID {}", account_id)))
+        }
+        result => result?,
+    };
+    let budget = select_budget_by_id(db, id)?.remove(0);
+    if account.currency.is_some()
+        && budget.currency.is_some()
+        && account.currency != budget.currency
+    {
+        return Err(Error::Invalid(format!(
+            "account {} and budget {} use different currencies",
+            account.name, budget.name
+        )));
+    }
+    Ok(())
+}
+
+fn check_spending_caps(db: &Connection, parts: &[(u32, f64)]) -> Result<(), Error> {
+    let budgets = select_all_budgets(db)?;
+    match exceeded_cap(&budgets, &get_reductions(db)?, parts) {
+        Some(parent) => Err(Error::Invalid(format!(
+            "this would take spending under {} over its cap of {}",
+            parent.name,
+            parent.spending_cap.unwrap_or_default()
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub fn reset_funds(
+    db: &Connection,
+    id: &u32,
+    command: &Command,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+) -> Result<usize, Error> {
+    change_funds(
+        db,
+        id,
+        &0.0,
+        &None,
+        command,
+        description,
+        date,
+        &None,
+        &None,
+    )
+}
+
+pub fn set_current_funds(
+    db: &Connection,
+    id: &u32,
+    amount: &f64,
+    expression: &Option<Expression>,
+    command: &Command,
+    description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+) -> Result<usize, Error> {
+    change_funds(
+        db,
+        id,
+        amount,
+        expression,
+        command,
+        description,
+        date,
+        &None,
+        &None,
+    )
+}
+
 pub fn set_initial_funds(
     db: &Connection,
     id: &u32,
     amount: &f64,
+    expression: &Option<Expression>,
     command: &Command,
     description: &Option<String>,
+    date: &Option<DateTime<Utc>>,
+) -> Result<usize, Error> {
+    change_funds(
+        db,
+        id,
+        amount,
+        expression,
+        command,
+        description,
+        date,
+        &None,
+        &None,
+    )
+}
+
+pub fn undo(
+    db: &Connection,
+    count: &u32,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<Vec<OperationSummary>, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let operations = undo_operations(&tx, count)?;
+    audit_operations(&tx, &operations, command, actor)?;
+    tx.commit()?;
+    Ok(operations)
+}
+
+pub fn redo(
+    db: &Connection,
+    count: &u32,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<Vec<OperationSummary>, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let operations = redo_operations(&tx, count)?;
+    audit_operations(&tx, &operations, command, actor)?;
+    tx.commit()?;
+    Ok(operations)
+}
+
+fn audit_operations(
+    db: &Connection,
+    operations: &[OperationSummary],
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<(), rusqlite::Error> {
+    for operation in operations {
+        for id in get_operation_budgets(db, operation)? {
+            let entry = AuditEntry::new(
+                &id,
+                command.value(),
+                "operation",
+                None,
+                Some(operation.action.to_owned()),
+                actor,
+            );
+            insert_audit_entry(db, &entry)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_budget(db: &Connection, id: &u32) -> Result<Budget, rusqlite::Error> {
+    Ok(select_budget_by_id(db, id)?.remove(0))
+}
+
+pub fn get_budgets(db: &Connection) -> Result<Vec<Budget>, rusqlite::Error> {
+    select_all_budgets(db)
+}
+
+pub fn get_budget_list(
+    db: &Connection,
+    sort: &Option<SortKey>,
+    status: &Option<Status>,
+    pattern: &Option<String>,
+    threshold: &f64,
+) -> Result<Vec<Budget>, rusqlite::Error> {
+    let mut budgets: Vec<Budget> = select_all_budgets(db)?
+        .into_iter()
+        .filter(|budget| status.is_none_or(|status| budget.status(threshold) == status))
+        .filter(|budget| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| matches_pattern(&budget.name, pattern))
+        })
+        .collect();
+    if let Some(sort) = sort {
+        sort_budgets(&mut budgets, sort);
+    }
+    Ok(budgets)
+}
+
+pub fn get_budget_tree(
+    db: &Connection,
+    sort: &Option<SortKey>,
+    status: &Option<Status>,
+    pattern: &Option<String>,
+    threshold: &f64,
+) -> Result<(Vec<Budget>, HashMap<u32, usize>), rusqlite::Error> {
+    let mut budgets: Vec<Budget> = roll_up(&select_all_budgets(db)?)
+        .into_iter()
+        .filter(|budget| status.is_none_or(|status| budget.status(threshold) == status))
+        .filter(|budget| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| matches_pattern(&budget.name, pattern))
+        })
+        .collect();
+    if let Some(sort) = sort {
+        sort_budgets(&mut budgets, sort);
+    }
+    Ok(arrange(budgets))
+}
+
+pub fn set_parent(
+    db: &Connection,
+    id: &u32,
+    parent: &Option<u32>,
+    base: &str,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, Error> {
+    let tx = db.unchecked_transaction()?;
+    let budgets = select_all_budgets(&tx)?;
+    let mut budget = select_budget_by_id(&tx, id)?.remove(0);
+    if let Some(parent) = parent {
+        let parent_budget = select_budget_by_id(&tx, parent)?.remove(0);
+        if parent == id || descendant_ids(&budgets, id).contains(parent) {
+            return Err(Error::Invalid(format!(
+                "{} cannot be placed under itself or one of its children",
+                budget.name
+            )));
+        }
+        let code = |budget: &Budget| budget.currency.clone().unwrap_or(base.to_string());
+        if code(&budget) != code(&parent_budget) {
+            return Err(Error::Invalid(format!(
+                "{} and {} use different currencies",
+                budget.name, parent_budget.name
+            )));
+        }
+    }
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let old_parent = budget.parent_id;
+    budget.parent_id = *parent;
+    let rows = update_budget(&tx, &budget)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "parent",
+        old_parent.map(|parent| parent.to_string()),
+        parent.map(|parent| parent.to_string()),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn set_spending_cap(
+    db: &Connection,
+    id: &u32,
+    cap: &Option<f64>,
+    command: &Command,
+    actor: &Option<String>,
 ) -> Result<usize, rusqlite::Error> {
-    let mut budgets = select_budget_by_id(db, id)?;
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let mut budgets = select_budget_by_id(&tx, id)?;
     let budget = &mut budgets[0];
-    budget.set_initial_funds(amount);
-    let rows = update_budget(db, budget)?;
-    let transaction = Record::new(id, command.value(), amount, description);
-    insert_record(db, &transaction)?;
+    let old_cap = budget.spending_cap;
+    budget.spending_cap = *cap;
+    let rows = update_budget(&tx, budget)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "cap",
+        old_cap.map(|cap| cap.to_string()),
+        cap.map(|cap| cap.to_string()),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
     Ok(rows)
 }
 
-pub fn get_budgets(db: &Connection) -> Result<Vec<Budget>, rusqlite::Error> {
-    select_all_budgets(db)
+pub fn get_spending(
+    db: &Connection,
+    id: &u32,
+    period: &Period,
+    count: &u32,
+    tz: &Option<Tz>,
+) -> Result<(Budget, Vec<PeriodTotal>), rusqlite::Error> {
+    let budget = get_budget(db, id)?;
+    let totals = get_spending_by_period(db, id, period, count, tz)?;
+    Ok((budget, totals))
+}
+
+pub fn get_monthly_report(
+    db: &Connection,
+    month: &NaiveDate,
+    tz: &Option<Tz>,
+    top: &u32,
+) -> Result<(Vec<MonthlyRow>, Vec<DescriptionTotal>), Error> {
+    let (start, end) = month_range(month, tz).ok_or(Error::Invalid(String::from(
+        "month does not exist in the selected timezone",
+    )))?;
+    let last_instant = Duration::nanoseconds(1);
+    let opening = get_budget_states_at(db, &(start - last_instant))?;
+    let closing = get_budget_states_at(db, &(end - last_instant))?;
+    let totals = get_action_totals(db, &start, &end)?;
+    let descriptions = get_top_descriptions(db, &start, &end, top)?;
+    Ok((
+        build_monthly_report(&opening, &closing, &totals),
+        descriptions,
+    ))
+}
+
+pub fn get_variance_report(
+    db: &Connection,
+    period: &Period,
+    comparison: &Comparison,
+    tz: &Option<Tz>,
+) -> Result<(String, String, Vec<VarianceRow>), Error> {
+    let today = today(tz);
+    let start = period.start_of(today);
+    let (baseline, baseline_end) = comparison.baseline_range(period, start, today);
+    let range = |start: NaiveDate, end: NaiveDate| {
+        date_range(&start, &end, tz).ok_or(Error::Invalid(String::from(
+            "period does not exist in the selected timezone",
+        )))
+    };
+    let (from, to) = range(start, today + Days::new(1))?;
+    let current_totals = get_action_totals(db, &from, &to)?;
+    let (from, to) = range(baseline, baseline_end)?;
+    let baseline_totals = get_action_totals(db, &from, &to)?;
+    let rows = build_variance_report(&select_all_budgets(db)?, &current_totals, &baseline_totals);
+    Ok((period.label(start), period.label(baseline), rows))
+}
+
+pub fn get_forecasts(
+    db: &Connection,
+    budgets: &[Budget],
+    window: &u32,
+    weighting: &Weighting,
+    tz: &Option<Tz>,
+) -> Result<HashMap<u32, Forecast>, rusqlite::Error> {
+    let today = today(tz);
+    let daily = get_daily_reductions(db, &today, window, tz)?;
+    let forecasts = budgets
+        .iter()
+        .filter_map(|budget| {
+            let id = budget.budget_id?;
+            let days = daily.get(&id).map(Vec::as_slice).unwrap_or_default();
+            Some((
+                id,
+                project(budget, burn_rate(days, window, weighting), &today),
+            ))
+        })
+        .collect();
+    Ok(forecasts)
+}
+
+pub fn get_trends(db: &Connection) -> Result<HashMap<u32, Vec<f64>>, rusqlite::Error> {
+    get_balance_trends(db, TREND_POINTS)
+}
+
+pub fn set_warning_threshold(
+    db: &Connection,
+    id: &u32,
+    threshold: &Option<f64>,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, rusqlite::Error> {
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let mut budgets = select_budget_by_id(&tx, id)?;
+    let budget = &mut budgets[0];
+    let old_threshold = budget.warning_threshold;
+    budget.warning_threshold = *threshold;
+    let rows = update_budget(&tx, budget)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "threshold",
+        old_threshold.map(|threshold| threshold.to_string()),
+        threshold.map(|threshold| threshold.to_string()),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn set_goal(
+    db: &Connection,
+    id: &u32,
+    target: &Option<(f64, NaiveDate)>,
+    tz: &Option<Tz>,
+    command: &Command,
+    actor: &Option<String>,
+) -> Result<usize, Error> {
+    if let Some((_, deadline)) = target {
+        if *deadline < today(tz) {
+            return Err(Error::Invalid(format!(
+                "the deadline {} is in the past",
+                deadline
+            )));
+        }
+    }
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, command.value(), Some(id))?;
+    operation.track("budgets", *id as i64)?;
+    let mut budgets = select_budget_by_id(&tx, id)?;
+    let budget = &mut budgets[0];
+    let old_goal = budget.goal.take();
+    let describe = |goal: &Goal| format!("{} by {}", goal.target, goal.deadline);
+    budget.goal = target.map(|(amount, deadline)| Goal {
+        target: amount,
+        deadline,
+        started: old_goal
+            .as_ref()
+            .map(|goal| goal.started)
+            .unwrap_or_else(|| today(tz)),
+    });
+    let rows = update_budget(&tx, budget)?;
+    let entry = AuditEntry::new(
+        id,
+        command.value(),
+        "goal",
+        old_goal.as_ref().map(describe),
+        budget.goal.as_ref().map(describe),
+        actor,
+    );
+    insert_audit_entry(&tx, &entry)?;
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn get_goals(
+    db: &Connection,
+    tz: &Option<Tz>,
+) -> Result<(Vec<Budget>, HashMap<u32, GoalProgress>), rusqlite::Error> {
+    let today = today(tz);
+    let budgets: Vec<Budget> = select_all_budgets(db)?
+        .into_iter()
+        .filter(|budget| budget.goal.is_some())
+        .collect();
+    let mut progress = HashMap::new();
+    for budget in &budgets {
+        let (Some(id), Some(goal)) = (budget.budget_id, &budget.goal) else {
+            continue;
+        };
+        let since = date_range(&goal.started, &goal.started, tz)
+            .map(|(start, _)| start)
+            .unwrap_or_default();
+        let contributed = get_contributions(db, &id, &since)?;
+        progress.insert(id, track_goal(budget, goal, contributed, &today));
+    }
+    Ok((budgets, progress))
+}
+
+pub fn get_conversion_rates(
+    db: &Connection,
+    budgets: &[Budget],
+    code: &str,
+    base: &str,
+) -> Result<Vec<f64>, Error> {
+    let mut rates = Vec::new();
+    for budget in budgets {
+        let from = budget.currency.clone().unwrap_or(base.to_string());
+        match find_rate(db, &from, code)? {
+            Some(rate) => rates.push(rate),
+            None => {
+                return Err(Error::Invalid(format!(
+                    "no exchange rate from {} to {}, add one with 'budget rates set'",
+                    from, code
+                )))
+            }
+        }
+    }
+    Ok(rates)
+}
+
+pub fn set_exchange_rate(
+    db: &Connection,
+    from: &str,
+    to: &str,
+    rate: &f64,
+) -> Result<usize, Error> {
+    let rates = vec![ExchangeRate::new(from, to, rate)];
+    save_exchange_rates(db, &rates, "Set exchange rate")
+}
+
+pub fn import_exchange_rates(db: &Connection, path: &Path) -> Result<usize, Error> {
+    let rates = parse_rates_csv(&fs::read_to_string(path)?)?;
+    save_exchange_rates(db, &rates, "Import exchange rates")
+}
+
+fn save_exchange_rates(
+    db: &Connection,
+    rates: &Vec<ExchangeRate>,
+    action: &str,
+) -> Result<usize, Error> {
+    for rate in rates {
+        if rate.base == rate.quote || !rate.rate.is_finite() || rate.rate <= 0.0 {
+            return Err(Error::Invalid(format!(
+                "invalid exchange rate {} from {} to {}",
+                rate.rate, rate.base, rate.quote
+            )));
+        }
+    }
+    let tx = db.unchecked_transaction()?;
+    let mut operation = Operation::begin(&tx, action, None)?;
+    let mut rows = 0;
+    for rate in rates {
+        match get_rate_row_id(&tx, &rate.base, &rate.quote)? {
+            Some(row_id) => {
+                operation.track("exchange_rates", row_id)?;
+                rows += upsert_rate(&tx, rate)?;
+            }
+            None => {
+                rows += upsert_rate(&tx, rate)?;
+                operation.track_new("exchange_rates", tx.last_insert_rowid())?;
+            }
+        }
+    }
+    operation.finish()?;
+    tx.commit()?;
+    Ok(rows)
+}
+
+pub fn get_exchange_rates(db: &Connection) -> Result<Vec<ExchangeRate>, rusqlite::Error> {
+    get_all_rates(db)
+}
+
+pub fn get_budgets_as_of(
+    db: &Connection,
+    instant: &DateTime<Utc>,
+) -> Result<Vec<BudgetState>, rusqlite::Error> {
+    get_budget_states_at(db, instant)
 }
 
-pub fn get_history(db: &Connection, id: &Option<u32>) -> Result<Vec<Record>, rusqlite::Error> {
+pub fn get_audit_log(
+    db: &Connection,
+    id: &Option<u32>,
+) -> Result<Vec<AuditEntry>, rusqlite::Error> {
     match id {
-        Some(id) => get_records_by_budget(db, id),
-        None => get_all_records(db),
+        Some(id) => get_audit_entries_by_budget(db, id),
+        None => get_all_audit_entries(db),
+    }
+}
+
+pub fn get_history(
+    db: &Connection,
+    id: &Option<u32>,
+    limit: &u32,
+    tree: &bool,
+) -> Result<Vec<Record>, rusqlite::Error> {
+    match (id, tree) {
+        (Some(id), true) => get_records_by_subtree(db, id, limit),
+        (Some(id), false) => get_records_by_budget(db, id, limit),
+        (None, _) => get_all_records(db, limit),
+    }
+}
+
+pub fn backup_database(db: &Connection, path: &Option<PathBuf>) -> Result<PathBuf, Error> {
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => default_backup_path(db)?,
+    };
+    backup_db(db, &path)?;
+    Ok(path)
+}
+
+pub fn restore_database(db: &mut Connection, path: &Path) -> Result<(), Error> {
+    restore_db(db, path)
+}
+
+pub fn take_snapshot(db: &Connection, keep: &usize) -> Result<PathBuf, Error> {
+    snapshot_db(db, *keep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{AccountAction, PayeeAction, SplitAction};
+    use crate::database::migrate;
+    use crate::money::find_locale;
+    use chrono::TimeZone;
+
+    fn setup(funds: &[(&str, f64)]) -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        for (name, amount) in funds {
+            create_budget(&db, name, amount, "USD", &new(), &None).unwrap();
+        }
+        db
+    }
+
+    fn new() -> Command {
+        Command::New {
+            name: String::new(),
+            funds: String::new(),
+            currency: None,
+        }
+    }
+
+    fn increase() -> Command {
+        Command::Increase {
+            id: 1,
+            amount: String::new(),
+            description: None,
+            date: None,
+            account: None,
+            payee: None,
+        }
+    }
+
+    fn reduce() -> Command {
+        Command::Reduce {
+            id: None,
+            amount: None,
+            description: None,
+            date: None,
+            split: Vec::new(),
+            account: None,
+            payee: None,
+        }
+    }
+
+    fn transfer() -> Command {
+        Command::Transfer {
+            from: 1,
+            to: 2,
+            amount: String::new(),
+            rate: None,
+            description: None,
+            date: None,
+        }
+    }
+
+    fn funds(db: &Connection, id: &u32) -> f64 {
+        get_budget(db, id).unwrap().current_funds
+    }
+
+    #[test]
+    fn post_record_backdated_ok() {
+        let db = setup(&[("Food", 100.0)]);
+        increase_funds(
+            &db,
+            &1,
+            &20.0,
+            &None,
+            &increase(),
+            &None,
+            &None,
+            &None,
+            &None,
+        )
+        .unwrap();
+        let date = Some(Utc::now() - Duration::try_days(3).unwrap());
+        reduce_funds(&db, &1, &30.0, &None, &reduce(), &None, &date, &None, &None).unwrap();
+        let records = get_records_by_budget(&db, &1, &10).unwrap();
+        let balances: Vec<(Option<f64>, Option<f64>)> = records
+            .iter()
+            .map(|record| (record.balance_before, record.balance_after))
+            .collect();
+        assert_eq!(
+            balances,
+            [(Some(70.0), Some(90.0)), (Some(100.0), Some(70.0))]
+        );
+        assert_eq!(funds(&db, &1), 90.0);
+    }
+
+    #[test]
+    fn undo_audit_ok() {
+        let db = setup(&[("Food", 300.0)]);
+        let actor = Some(String::from("alice"));
+        let rename = Command::Rename {
+            id: 1,
+            name: String::from("Groceries"),
+        };
+        rename_budget(&db, &1, "Groceries", &rename, &actor).unwrap();
+        undo(&db, &1, &Command::Undo { count: 1 }, &actor).unwrap();
+        redo(&db, &1, &Command::Redo { count: 1 }, &actor).unwrap();
+        let entries = get_audit_log(&db, &Some(1)).unwrap();
+        let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(actions, ["Redo", "Undo", "Rename budget", "Create budget"]);
+        assert_eq!(entries[0].new_value, Some(String::from("Rename budget")));
+        assert_eq!(get_budget(&db, &1).unwrap().name, "Groceries");
+    }
+
+    #[test]
+    fn settle_keeps_redo_ok() {
+        let db = setup(&[("Food", 300.0), ("Rent", 900.0)]);
+        let tomorrow = Some(Utc::now() + Duration::try_days(1).unwrap());
+        reduce_funds(
+            &db,
+            &2,
+            &50.0,
+            &None,
+            &reduce(),
+            &None,
+            &tomorrow,
+            &None,
+            &None,
+        )
+        .unwrap();
+        reduce_funds(&db, &1, &20.0, &None, &reduce(), &None, &None, &None, &None).unwrap();
+        undo(&db, &1, &Command::Undo { count: 1 }, &None).unwrap();
+        db.execute(
+            "UPDATE records SET created_at = ?1 WHERE pending = 1;",
+            [Utc::now() - Duration::try_days(1).unwrap()],
+        )
+        .unwrap();
+        assert_eq!(settle_pending_records(&db).unwrap(), 1);
+        assert_eq!(
+            redo(&db, &1, &Command::Redo { count: 1 }, &None)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(funds(&db, &1), 280.0);
+        assert_eq!(funds(&db, &2), 850.0);
+    }
+
+    #[test]
+    fn transfer_funds_ok() {
+        let db = setup(&[("Food", 300.0), ("Rent", 900.0)]);
+        create_budget(&db, "Trip", &0.0, "EUR", &new(), &None).unwrap();
+        let command = transfer();
+        transfer_funds(&db, &1, &2, &100.0, &None, "USD", &command, &None, &None).unwrap();
+        assert_eq!(funds(&db, &1), 200.0);
+        assert_eq!(funds(&db, &2), 1000.0);
+        transfer_funds(
+            &db,
+            &1,
+            &3,
+            &100.0,
+            &Some(0.9),
+            "USD",
+            &command,
+            &None,
+            &None,
+        )
+        .unwrap();
+        assert_eq!(funds(&db, &1), 100.0);
+        assert_eq!(funds(&db, &3), 90.0);
+    }
+
+    #[test]
+    fn transfer_funds_ko() {
+        let db = setup(&[("Food", 300.0), ("Rent", 900.0)]);
+        create_budget(&db, "Trip", &0.0, "EUR", &new(), &None).unwrap();
+        let command = transfer();
+        for (to, rate) in [(2, 1.1), (3, 0.0), (3, -0.9)] {
+            assert!(transfer_funds(
+                &db,
+                &1,
+                &to,
+                &100.0,
+                &Some(rate),
+                "USD",
+                &command,
+                &None,
+                &None
+            )
+            .is_err());
+        }
+        assert!(transfer_funds(&db, &1, &1, &100.0, &None, "USD", &command, &None, &None).is_err());
+        for amount in [0.0, -50.0] {
+            assert!(
+                transfer_funds(&db, &1, &2, &amount, &None, "USD", &command, &None, &None).is_err()
+            );
+        }
+        assert_eq!(funds(&db, &1), 300.0);
+        assert_eq!(funds(&db, &2), 900.0);
+        assert_eq!(funds(&db, &3), 0.0);
+    }
+
+    #[test]
+    fn set_goal_ok() {
+        let db = setup(&[("Vacation", 500.0)]);
+        let tz = Some(Tz::UTC);
+        let command = Command::Goal {
+            id: 1,
+            target: None,
+            deadline: None,
+            clear: false,
+        };
+        let deadline = today(&tz) + chrono::Days::new(30);
+        let past = today(&tz) - chrono::Days::new(1);
+        assert!(set_goal(&db, &1, &Some((1000.0, past)), &tz, &command, &None).is_err());
+        set_goal(&db, &1, &Some((1000.0, deadline)), &tz, &command, &None).unwrap();
+        increase_funds(
+            &db,
+            &1,
+            &250.0,
+            &None,
+            &increase(),
+            &None,
+            &None,
+            &None,
+            &None,
+        )
+        .unwrap();
+        let (_, progress) = get_goals(&db, &tz).unwrap();
+        assert_eq!(progress[&1].saved, 750.0);
+        assert_eq!(progress[&1].contributed, 250.0);
+        assert_eq!(progress[&1].percent, 25.0);
+    }
+
+    fn reconcile() -> Command {
+        Command::Reconcile {
+            id: None,
+            account: None,
+            statement_balance: String::new(),
+            as_of: None,
+            clear: Vec::new(),
+            unclear: Vec::new(),
+            finish: false,
+        }
+    }
+
+    fn spend(db: &Connection, id: u32, amount: f64, day: u32, account: Option<u32>) -> bool {
+        let date = Some(Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap());
+        let command = reduce();
+        reduce_funds(
+            db, &id, &amount, &None, &command, &None, &date, &account, &None,
+        )
+        .is_ok()
+    }
+
+    #[test]
+    fn reconcile_budget_ok() {
+        let db = setup(&[("Food", 500.0)]);
+        assert!(spend(&db, 1, 40.0, 2, None));
+        assert!(spend(&db, 1, 60.0, 5, None));
+        let as_of = Utc.with_ymd_and_hms(2025, 3, 31, 0, 0, 0).unwrap();
+        let reconciliation =
+            reconcile_budget(&db, &1, &460.0, &as_of, &[1], &[], &true, &reconcile()).unwrap();
+        assert_eq!(reconciliation.records.len(), 1);
+        assert_eq!(
+            last_reconciled_at(&db, &1).unwrap(),
+            Some(Utc.with_ymd_and_hms(2025, 3, 2, 12, 0, 0).unwrap())
+        );
+        assert!(!spend(&db, 1, 5.0, 1, None));
+        assert!(!spend(&db, 1, 5.0, 2, None));
+        assert!(spend(&db, 1, 5.0, 3, None));
+        assert_eq!(funds(&db, &1), 395.0);
+    }
+
+    #[test]
+    fn reconcile_budget_ko() {
+        let db = setup(&[("Food", 500.0)]);
+        assert!(spend(&db, 1, 40.0, 2, None));
+        let as_of = Utc.with_ymd_and_hms(2025, 3, 31, 0, 0, 0).unwrap();
+        let command = reconcile();
+        assert!(reconcile_budget(&db, &1, &400.0, &as_of, &[1], &[], &true, &command).is_err());
+        assert!(reconcile_budget(&db, &1, &460.0, &as_of, &[7], &[], &false, &command).is_err());
+        assert_eq!(last_reconciled_at(&db, &1).unwrap(), None);
+        assert!(spend(&db, 1, 5.0, 1, None));
+    }
+
+    #[test]
+    fn reconcile_account_ok() {
+        let db = setup(&[("Food", 500.0), ("Rent", 900.0)]);
+        open_account(&db, "Checking", AccountKind::Bank, 1000.0);
+        assert!(spend(&db, 1, 40.0, 2, Some(1)));
+        assert!(spend(&db, 2, 60.0, 5, Some(1)));
+        assert!(spend(&db, 2, 10.0, 6, None));
+        let as_of = Utc.with_ymd_and_hms(2025, 3, 31, 0, 0, 0).unwrap();
+        let command = reconcile();
+        let reconciliation =
+            reconcile_account(&db, &1, &900.0, &as_of, &[], &[], &false, &command).unwrap();
+        assert_eq!(reconciliation.records.len(), 2);
+        assert_eq!(reconciliation.balance, 900.0);
+        reconcile_account(&db, &1, &900.0, &as_of, &[1, 2], &[], &true, &command).unwrap();
+        assert!(!spend(&db, 1, 5.0, 4, Some(1)));
+        assert!(spend(&db, 1, 5.0, 4, None));
+        assert!(spend(&db, 1, 5.0, 7, Some(1)));
+    }
+
+    fn open_account(db: &Connection, name: &str, kind: AccountKind, balance: f64) {
+        let command = Command::Account {
+            action: AccountAction::New {
+                name: String::new(),
+                balance: String::new(),
+                kind,
+                currency: None,
+            },
+        };
+        create_account(db, name, &kind, &balance, "USD", &command).unwrap();
+    }
+
+    fn remove() -> Command {
+        Command::Account {
+            action: AccountAction::Remove { id: 1 },
+        }
+    }
+
+    fn income() -> Command {
+        Command::Income {
+            amount: String::new(),
+            description: None,
+            date: None,
+            account: None,
+        }
+    }
+
+    fn assign() -> Command {
+        Command::Assign {
+            id: 1,
+            amount: String::new(),
+            description: None,
+            date: None,
+        }
+    }
+
+    #[test]
+    fn assign_income_ok() {
+        let db = setup(&[("Food", 0.0)]);
+        create_budget(&db, "Trip", &0.0, "EUR", &new(), &None).unwrap();
+        set_exchange_rate(&db, "USD", "EUR", &0.9).unwrap();
+        add_income(&db, &1000.0, &income(), &None, &None, &None).unwrap();
+        assign_income(&db, &1, &400.0, "USD", &assign(), &None, &None).unwrap();
+        assign_income(&db, &2, &100.0, "USD", &assign(), &None, &None).unwrap();
+        assert_eq!(funds(&db, &1), 400.0);
+        assert_eq!(funds(&db, &2), 90.0);
+        assert_eq!(get_unassigned(&db).unwrap(), Some(500.0));
+    }
+
+    #[test]
+    fn assign_income_ko() {
+        let db = setup(&[("Food", 0.0)]);
+        create_budget(&db, "Trip", &0.0, "EUR", &new(), &None).unwrap();
+        add_income(&db, &100.0, &income(), &None, &None, &None).unwrap();
+        assert!(assign_income(&db, &1, &150.0, "USD", &assign(), &None, &None).is_err());
+        assert!(assign_income(&db, &2, &50.0, "USD", &assign(), &None, &None).is_err());
+        assert_eq!(funds(&db, &1), 0.0);
+        assert_eq!(get_unassigned(&db).unwrap(), Some(100.0));
+    }
+
+    #[test]
+    fn account_balance_ok() {
+        let db = setup(&[("Food", 100.0), ("Fun", 50.0)]);
+        open_account(&db, "Checking", AccountKind::Bank, 1000.0);
+        let account = Some(1);
+        increase_funds(
+            &db,
+            &1,
+            &100.0,
+            &None,
+            &increase(),
+            &None,
+            &None,
+            &account,
+            &None,
+        )
+        .unwrap();
+        reduce_funds(
+            &db,
+            &1,
+            &40.0,
+            &None,
+            &reduce(),
+            &None,
+            &None,
+            &account,
+            &None,
+        )
+        .unwrap();
+        assert_eq!(select_account_by_id(&db, &1).unwrap().balance, 1060.0);
+        let parts = [(1, 10.0, None), (2, 5.0, None)];
+        reduce_split(&db, &parts, &reduce(), &None, &None, &account, &None).unwrap();
+        assert_eq!(select_account_by_id(&db, &1).unwrap().balance, 1045.0);
+        let delete = Command::Split {
+            action: SplitAction::Delete { id: 1 },
+        };
+        delete_split(&db, &1, &delete).unwrap();
+        assert_eq!(select_account_by_id(&db, &1).unwrap().balance, 1060.0);
+    }
+
+    #[test]
+    fn remove_account_ko() {
+        let db = setup(&[("Food", 100.0)]);
+        open_account(&db, "Checking", AccountKind::Bank, 1000.0);
+        open_account(&db, "Wallet", AccountKind::Cash, 50.0);
+        reduce_funds(
+            &db,
+            &1,
+            &40.0,
+            &None,
+            &reduce(),
+            &None,
+            &None,
+            &Some(1),
+            &None,
+        )
+        .unwrap();
+        add_income(&db, &20.0, &income(), &None, &None, &Some(2)).unwrap();
+        assert!(remove_account(&db, &1, &remove()).is_err());
+        assert!(remove_account(&db, &2, &remove()).is_err());
+        open_account(&db, "Savings", AccountKind::Bank, 0.0);
+        assert_eq!(remove_account(&db, &3, &remove()).unwrap(), 1);
+        assert_eq!(select_all_accounts(&db).unwrap().len(), 2);
+    }
+
+    fn alias() -> Command {
+        Command::Payee {
+            action: PayeeAction::Alias {
+                id: 1,
+                alias: String::new(),
+            },
+        }
+    }
+
+    fn spend_at(db: &Connection, id: u32, amount: f64, payee: &str) {
+        let payee = Some(payee.to_string());
+        let command = reduce();
+        reduce_funds(
+            db, &id, &amount, &None, &command, &None, &None, &None, &payee,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn payee_report_ok() {
+        let db = setup(&[("Food", 500.0), ("Fun", 100.0)]);
+        spend_at(&db, 1, 40.0, "LIDL 0442");
+        spend_at(&db, 1, 10.0, "Lidl store");
+        spend_at(&db, 2, 5.0, "7-Eleven");
+        spend_at(&db, 2, 3.0, "Eleven");
+        add_payee_alias(&db, &1, "Lidl Plus", &alias(), &None).unwrap();
+        spend_at(&db, 1, 20.0, "lidl plus");
+        let names: Vec<String> = get_payees(&db, &None)
+            .unwrap()
+            .into_iter()
+            .map(|payee| payee.name)
+            .collect();
+        assert_eq!(names, ["Lidl", "7-eleven", "Eleven"]);
+        let today = today(&None);
+        let totals = get_payee_report(&db, &today, &today, &None).unwrap();
+        assert_eq!(totals.len(), 3);
+        assert_eq!((totals[0].name.as_str(), totals[0].count), ("Lidl", 3));
+        assert_eq!(totals[0].total, 70.0);
+        assert_eq!(totals[1].total, 5.0);
+        let entries = get_audit_log(&db, &None).unwrap();
+        assert_eq!(entries[0].budget_id, None);
+        assert_eq!(entries[0].new_value, Some(String::from("lidl plus (Lidl)")));
+    }
+
+    #[test]
+    fn add_payee_alias_ko() {
+        let db = setup(&[("Food", 500.0)]);
+        spend_at(&db, 1, 40.0, "Lidl");
+        spend_at(&db, 1, 10.0, "Aldi");
+        assert!(add_payee_alias(&db, &1, "Aldi", &alias(), &None).is_err());
+        assert!(add_payee_alias(&db, &1, "LIDL 0442", &alias(), &None).is_err());
+        assert!(add_payee_alias(&db, &3, "Netto", &alias(), &None).is_err());
+    }
+
+    #[test]
+    fn check_spending_caps_ko() {
+        let db = setup(&[("House", 0.0), ("Food", 300.0), ("Fun", 100.0)]);
+        let parent = Command::Parent {
+            id: 2,
+            parent: Some(1),
+        };
+        set_parent(&db, &2, &Some(1), "USD", &parent, &None).unwrap();
+        let cap = Command::Cap {
+            id: 1,
+            amount: None,
+        };
+        set_spending_cap(&db, &1, &Some(50.0), &cap, &None).unwrap();
+        reduce_funds(&db, &2, &40.0, &None, &reduce(), &None, &None, &None, &None).unwrap();
+        increase_funds(
+            &db,
+            &2,
+            &100.0,
+            &None,
+            &increase(),
+            &None,
+            &None,
+            &None,
+            &None,
+        )
+        .unwrap();
+        assert!(
+            reduce_funds(&db, &2, &20.0, &None, &reduce(), &None, &None, &None, &None).is_err()
+        );
+        assert!(
+            transfer_funds(&db, &2, &3, &20.0, &None, "USD", &transfer(), &None, &None).is_err()
+        );
+        assert_eq!(funds(&db, &2), 360.0);
+    }
+
+    #[test]
+    fn reduce_split_ok() {
+        let db = setup(&[("Food", 100.0), ("Fun", 50.0)]);
+        let parts = [(1, 10.0, None), (1, 5.0, None), (2, 7.0, None)];
+        let rows = reduce_split(&db, &parts, &reduce(), &None, &None, &None, &None).unwrap();
+        assert_eq!(rows, 3);
+        assert_eq!(funds(&db, &1), 85.0);
+        assert_eq!(funds(&db, &2), 43.0);
+    }
+
+    #[test]
+    fn reduce_expression_ok() {
+        let db = setup(&[("Food", 200.0), ("Fun", 50.0)]);
+        let expression = |text: &str| Some(Expression::new(text, &find_locale("en-US").unwrap()));
+        reduce_funds(
+            &db,
+            &1,
+            &0.0,
+            &expression("10%"),
+            &reduce(),
+            &None,
+            &None,
+            &None,
+            &None,
+        )
+        .unwrap();
+        assert_eq!(funds(&db, &1), 180.0);
+        let parts = [(1, 0.0, expression("50% - 40")), (2, 5.0, None)];
+        reduce_split(&db, &parts, &reduce(), &None, &None, &None, &None).unwrap();
+        assert_eq!(funds(&db, &1), 130.0);
+        assert_eq!(funds(&db, &2), 45.0);
+        let history = get_history(&db, &Some(1), &10, &false).unwrap();
+        assert!(history
+            .iter()
+            .any(|record| record.expression.as_deref() == Some("10%")));
+        assert!(reduce_funds(
+            &db,
+            &1,
+            &0.0,
+            &expression("10%*"),
+            &reduce(),
+            &None,
+            &None,
+            &None,
+            &None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn reduce_split_ko() {
+        let db = setup(&[("Food", 100.0), ("Fun", 50.0)]);
+        for parts in [
+            [(1, 10.0, None), (2, 0.0, None)],
+            [(1, 10.0, None), (2, -4.0, None)],
+        ] {
+            assert!(reduce_split(&db, &parts, &reduce(), &None, &None, &None, &None).is_err());
+        }
+        assert_eq!(funds(&db, &1), 100.0);
+        assert_eq!(funds(&db, &2), 50.0);
+    }
+
+    #[test]
+    fn delete_split_ok() {
+        let db = setup(&[("Food", 100.0), ("Fun", 50.0)]);
+        let parts = [(1, 10.0, None), (1, 5.0, None), (2, 7.0, None)];
+        reduce_split(&db, &parts, &reduce(), &None, &None, &None, &None).unwrap();
+        let command = Command::Split {
+            action: SplitAction::Delete { id: 1 },
+        };
+        assert_eq!(delete_split(&db, &1, &command).unwrap(), 3);
+        assert_eq!(funds(&db, &1), 100.0);
+        assert_eq!(funds(&db, &2), 50.0);
+        assert!(get_records_by_split(&db, &1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn edit_split_ok() {
+        let db = setup(&[("Food", 100.0), ("Fun", 50.0)]);
+        let parts = [(1, 10.0, None), (1, 5.0, None), (2, 7.0, None)];
+        reduce_split(&db, &parts, &reduce(), &None, &None, &None, &None).unwrap();
+        let date = Utc::now() - Duration::try_days(2).unwrap();
+        let command = Command::Split {
+            action: SplitAction::Edit {
+                id: 1,
+                description: None,
+                date: None,
+            },
+        };
+        edit_split(
+            &db,
+            &1,
+            &Some(String::from("market")),
+            &Some(date),
+            &command,
+        )
+        .unwrap();
+        assert_eq!(funds(&db, &1), 85.0);
+        assert_eq!(funds(&db, &2), 43.0);
+        let records = get_records_by_split(&db, &1).unwrap();
+        let balances: Vec<(Option<f64>, Option<f64>)> = records
+            .iter()
+            .map(|record| (record.balance_before, record.balance_after))
+            .collect();
+        assert_eq!(
+            balances,
+            [
+                (Some(100.0), Some(90.0)),
+                (Some(90.0), Some(85.0)),
+                (Some(50.0), Some(43.0))
+            ]
+        );
+        assert!(records
+            .iter()
+            .all(|record| record.created_at == date && record.desc.as_deref() == Some("market")));
     }
 }
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+pub struct Split {
+    pub split_id: Option<u32>,
+    pub desc: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn create_split_tables(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE TABLE IF NOT EXISTS splits (
+            split_id INTEGER PRIMARY KEY,
+            description TEXT,
+            created_at TEXT NOT NULL
+        );
+        ALTER TABLE records ADD COLUMN split_id INTEGER REFERENCES splits(split_id);
+        CREATE INDEX IF NOT EXISTS records_split ON records (split_id);";
+    db.execute_batch(query)
+}
+
+pub fn insert_split(db: &Connection, split: &Split) -> Result<usize, rusqlite::Error> {
+    let query = "
+        INSERT INTO splits (description, created_at)
+        VALUES (?1, ?2);";
+    db.execute(query, (&split.desc, &split.created_at))
+}
+
+pub fn select_split_by_id(db: &Connection, id: &u32) -> Result<Split, rusqlite::Error> {
+    let query = "
+        SELECT split_id, description, created_at
+        FROM splits
+        WHERE split_id = ?1;";
+    db.query_row(query, [id], |row| {
+        Ok(Split {
+            split_id: row.get(0)?,
+            desc: row.get(1)?,
+            created_at: row.get(2)?,
+        })
+    })
+}
+
+pub fn update_split(db: &Connection, split: &Split) -> Result<usize, rusqlite::Error> {
+    let query = "
+        UPDATE splits
+        SET description = ?1,
+            created_at = ?2
+        WHERE split_id = ?3;";
+    db.execute(query, (&split.desc, &split.created_at, &split.split_id))
+}
+
+pub fn delete_split_by_id(db: &Connection, id: &u32) -> Result<usize, rusqlite::Error> {
+    db.execute("DELETE FROM splits WHERE split_id = ?1;", [id])
+}
+
+pub fn parse_split(text: &str) -> Result<(u32, String), String> {
+    let error = || format!("invalid split '{}', expected ID:AMOUNT", text);
+    let (id, amount) = text.split_once(':').ok_or_else(error)?;
+    let id = id.trim().parse().map_err(|_| error())?;
+    if amount.trim().is_empty() {
+        return Err(error());
+    }
+    Ok((id, amount.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_split_ok() {
+        assert_eq!(parse_split("3:42.10"), Ok((3, String::from("42.10"))));
+        assert_eq!(parse_split("7: 20"), Ok((7, String::from("20"))));
+    }
+
+    #[test]
+    fn parse_split_ko() {
+        assert!(parse_split("42.10").is_err());
+        assert!(parse_split("food:10").is_err());
+        assert!(parse_split("3:").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::color::paint;
+use std::env;
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const GAP: &str = "  ";
+const ELLIPSIS: char = '…';
+const MIN_WIDTH: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    Keep,
+    Truncate,
+    Wrap,
+}
+
+pub struct Column {
+    header: String,
+    align: Align,
+    overflow: Overflow,
+}
+
+impl Column {
+    pub fn left(header: &str) -> Column {
+        Column {
+            header: header.to_string(),
+            align: Align::Left,
+            overflow: Overflow::Truncate,
+        }
+    }
+
+    pub fn right(header: &str) -> Column {
+        Column {
+            header: header.to_string(),
+            align: Align::Right,
+            overflow: Overflow::Keep,
+        }
+    }
+
+    pub fn keep(mut self) -> Column {
+        self.overflow = Overflow::Keep;
+        self
+    }
+
+    pub fn wrap(mut self) -> Column {
+        self.overflow = Overflow::Wrap;
+        self
+    }
+
+    fn min_width(&self, natural: usize) -> usize {
+        match self.overflow {
+            Overflow::Keep => natural,
+            _ => natural.min(self.header.width().max(MIN_WIDTH)),
+        }
+    }
+}
+
+enum Row {
+    Cells(Vec<String>, Vec<Option<&'static str>>),
+    Separator,
+}
+
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+    header_style: Option<&'static str>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Table {
+        Table {
+            columns,
+            rows: Vec::new(),
+            header_style: None,
+        }
+    }
+
+    pub fn set_header_style(&mut self, style: Option<&'static str>) {
+        self.header_style = style;
+    }
+
+    pub fn add_row(&mut self, cells: Vec<String>) {
+        let styles = vec![None; cells.len()];
+        self.rows.push(Row::Cells(cells, styles));
+    }
+
+    pub fn style_cell(&mut self, column: usize, style: Option<&'static str>) {
+        if let Some(Row::Cells(_, styles)) = self.rows.last_mut() {
+            if let Some(cell_style) = styles.get_mut(column) {
+                *cell_style = style;
+            }
+        }
+    }
+
+    pub fn add_separator(&mut self) {
+        self.rows.push(Row::Separator);
+    }
+
+    pub fn print(&self) {
+        print!("\n{}", self.render(terminal_width()));
+    }
+
+    pub fn render(&self, max_width: usize) -> String {
+        let widths = self.widths(max_width);
+        let total = widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+        let headers: Vec<String> = self.columns.iter().map(|c| c.header.clone()).collect();
+        let header_styles = vec![self.header_style; headers.len()];
+        let mut output = self.render_cells(&headers, &header_styles, &widths);
+        output.push_str(&"-".repeat(total));
+        output.push('\n');
+        for row in &self.rows {
+            match row {
+                Row::Cells(cells, styles) => {
+                    output.push_str(&self.render_cells(cells, styles, &widths))
+                }
+                Row::Separator => {
+                    output.push_str(&"-".repeat(total));
+                    output.push('\n');
+                }
+            }
+        }
+        output
+    }
+
+    fn widths(&self, max_width: usize) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| match row {
+                        Row::Cells(cells, _) => cells.get(index).map(|cell| cell.width()),
+                        Row::Separator => None,
+                    })
+                    .fold(column.header.width(), usize::max)
+            })
+            .collect();
+        let minimums: Vec<usize> = self
+            .columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, natural)| column.min_width(*natural))
+            .collect();
+        let gaps = GAP.len() * widths.len().saturating_sub(1);
+        let mut excess = (widths.iter().sum::<usize>() + gaps).saturating_sub(max_width);
+        while excess > 0 {
+            let widest = (0..widths.len())
+                .filter(|index| widths[*index] > minimums[*index])
+                .max_by_key(|index| {
+                    let wraps = self.columns[*index].overflow == Overflow::Wrap;
+                    (wraps, widths[*index] - minimums[*index])
+                });
+            match widest {
+                Some(index) => widths[index] -= 1,
+                None => break,
+            }
+            excess -= 1;
+        }
+        widths
+    }
+
+    fn render_cells(
+        &self,
+        cells: &[String],
+        styles: &[Option<&'static str>],
+        widths: &[usize],
+    ) -> String {
+        let lines: Vec<Vec<String>> = self
+            .columns
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(index, (column, width))| {
+                let cell = cells.get(index).map(String::as_str).unwrap_or_default();
+                match column.overflow {
+                    Overflow::Wrap => wrap(cell, *width),
+                    _ => vec![truncate(cell, *width)],
+                }
+            })
+            .collect();
+        let height = lines.iter().map(Vec::len).max().unwrap_or(1);
+        let mut output = String::new();
+        for line in 0..height {
+            let parts: Vec<String> = self
+                .columns
+                .iter()
+                .zip(widths)
+                .zip(&lines)
+                .enumerate()
+                .map(|(index, ((column, width), cell))| {
+                    let text = cell.get(line).map(String::as_str).unwrap_or_default();
+                    let style = styles.get(index).copied().flatten();
+                    pad(text, *width, column.align, style)
+                })
+                .collect();
+            output.push_str(parts.join(GAP).trim_end());
+            output.push('\n');
+        }
+        output
+    }
+}
+
+pub fn terminal_width() -> usize {
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return columns;
+    }
+    match terminal_size() {
+        Some((Width(width), _)) => width.into(),
+        None => usize::MAX,
+    }
+}
+
+pub fn pad(text: &str, width: usize, align: Align, style: Option<&str>) -> String {
+    let padding = " ".repeat(width.saturating_sub(text.width()));
+    match align {
+        Align::Left => format!("{}{}", paint(text, style), padding),
+        Align::Right => format!("{}{}", padding, paint(text, style)),
+    }
+}
+
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        truncated.push(ELLIPSIS);
+    }
+    truncated
+}
+
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.width() > width && width > 0 {
+            let (head, tail) = split_at_width(&word, width);
+            lines.push(head);
+            word = tail;
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn split_at_width(text: &str, width: usize) -> (String, String) {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width && index > 0 {
+            return (text[..index].to_string(), text[index..].to_string());
+        }
+    }
+    (text.to_string(), String::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_ok() {
+        assert_eq!(truncate("Groceries", 20), "Groceries");
+        assert_eq!(truncate("Groceries", 6), "Groce…");
+        assert_eq!(truncate("日本旅行", 5), "日本…");
+    }
+
+    #[test]
+    fn wrap_ok() {
+        assert_eq!(
+            wrap("weekly shop at the market", 10),
+            ["weekly", "shop at", "the market"]
+        );
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), [""]);
+    }
+
+    #[test]
+    fn render_ok() {
+        let mut table = Table::new(vec![Column::left("NAME"), Column::right("FUNDS")]);
+        table.add_row(vec![String::from("🍕 Food"), String::from("-$5.00")]);
+        table.add_row(vec![String::from("Rent"), String::from("$800.00")]);
+        let expected = "NAME       FUNDS\n----------------\n🍕 Food   -$5.00\nRent     $800.00\n";
+        assert_eq!(table.render(80), expected);
+        let narrow = table.render(14);
+        assert!(narrow.lines().all(|line| line.width() <= 14));
+        assert!(narrow.contains("🍕 F…"));
+    }
+}
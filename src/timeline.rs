@@ -0,0 +1,381 @@
+use crate::color::Role;
+use crate::config::Config;
+use crate::dates::format_datetime;
+use crate::output::{print_rows, Cell, OutputFormat};
+use crate::table::{Column, Table};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+pub struct BudgetState {
+    pub budget_id: u32,
+    pub name: Option<String>,
+    pub initial_funds: Option<f64>,
+    pub current_funds: Option<f64>,
+    pub currency: Option<String>,
+}
+
+enum Presence {
+    Existed(Option<String>),
+    Absent,
+    Unknown,
+}
+
+struct NameChange {
+    action: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+}
+
+impl NameChange {
+    fn before(&self) -> Presence {
+        match self.action.as_str() {
+            "Create budget" => Presence::Absent,
+            _ => Presence::Existed(self.old_value.to_owned()),
+        }
+    }
+
+    fn after(&self) -> Presence {
+        match self.action.as_str() {
+            "Remove budget" => Presence::Absent,
+            _ => Presence::Existed(self.new_value.to_owned()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct NameHistory {
+    first: Option<Presence>,
+    applied: Vec<NameChange>,
+    undone: Vec<NameChange>,
+}
+
+impl NameHistory {
+    fn presence(self) -> Presence {
+        match (self.applied.last(), self.first) {
+            (Some(change), _) => change.after(),
+            (None, Some(first)) => first,
+            (None, None) => Presence::Unknown,
+        }
+    }
+}
+
+pub fn print_budget_states(states: &Vec<BudgetState>, instant: &DateTime<Utc>, config: &Config) {
+    if config.output != OutputFormat::Table {
+        let rows: Vec<Vec<Cell>> = states
+            .iter()
+            .map(|state| {
+                vec![
+                    Cell::Number(state.budget_id.into()),
+                    Cell::optional_text(&state.name),
+                    Cell::Text(config.currency_code(&state.currency)),
+                    Cell::optional_number(state.current_funds),
+                    Cell::optional_number(state.initial_funds),
+                ]
+            })
+            .collect();
+        let headers = ["id", "budget", "currency", "current_funds", "initial_funds"];
+        return print_rows(&config.output, &headers, &rows);
+    }
+    println!(
+        "\nAs of {}",
+        format_datetime(instant, &config.tz(), &config.datetime_format())
+    );
+    let mut table = Table::new(vec![
+        Column::right("ID"),
+        Column::left("BUDGET"),
+        Column::left("CURRENCY").keep(),
+        Column::right("CURRENT FUNDS"),
+        Column::right("INITIAL FUNDS"),
+    ]);
+    table.set_header_style(config.style(Role::Header));
+    for state in states {
+        let money = config.money_format(&state.currency);
+        let format_funds = |funds: Option<f64>| match funds {
+            Some(funds) => money.format(funds),
+            None => String::from("-"),
+        };
+        table.add_row(vec![
+            state.budget_id.to_string(),
+            state.name.clone().unwrap_or_default(),
+            config.currency_code(&state.currency),
+            format_funds(state.current_funds),
+            format_funds(state.initial_funds),
+        ]);
+        table.style_cell(
+            3,
+            config.amount_style(state.current_funds.unwrap_or_default()),
+        );
+    }
+    table.print();
+}
+
+pub fn create_timeline_indexes(db: &Connection) -> Result<(), rusqlite::Error> {
+    let query = "
+        CREATE INDEX IF NOT EXISTS records_budget_date
+        ON records (budget_id, created_at, record_id);
+        CREATE INDEX IF NOT EXISTS audit_budget_date
+        ON audit (budget_id, created_at, audit_id);";
+    db.execute_batch(query)
+}
+
+pub fn get_budget_states_at(
+    db: &Connection,
+    instant: &DateTime<Utc>,
+) -> Result<Vec<BudgetState>, rusqlite::Error> {
+    let query = "
+        WITH ids AS (
+            SELECT budget_id FROM budgets
+            UNION SELECT budget_id FROM records
+            UNION SELECT budget_id FROM audit WHERE budget_id IS NOT NULL
+        ),
+        last_record AS (
+            SELECT budget_id, balance_after, ROW_NUMBER() OVER (
+                PARTITION BY budget_id ORDER BY created_at DESC, record_id DESC
+            ) AS n
+            FROM records
+            WHERE pending = 0 AND created_at <= ?1
+        ),
+        next_record AS (
+            SELECT budget_id, balance_before, ROW_NUMBER() OVER (
+                PARTITION BY budget_id ORDER BY created_at ASC, record_id ASC
+            ) AS n
+            FROM records
+            WHERE pending = 0 AND created_at > ?1
+        ),
+        last_initial AS (
+            SELECT budget_id, amount, ROW_NUMBER() OVER (
+                PARTITION BY budget_id ORDER BY created_at DESC, record_id DESC
+            ) AS n
+            FROM records
+            WHERE pending = 0 AND action = 'Set initial funds' AND created_at <= ?1
+        ),
+        later_initial AS (
+            SELECT DISTINCT budget_id
+            FROM records
+            WHERE pending = 0 AND action = 'Set initial funds' AND created_at > ?1
+        )
+        SELECT i.budget_id, b.name, b.initial_funds, b.current_funds, b.currency,
+            lr.budget_id IS NOT NULL, lr.balance_after,
+            nr.budget_id IS NOT NULL, nr.balance_before,
+            li.budget_id IS NOT NULL, li.amount,
+            la.budget_id IS NOT NULL
+        FROM ids i
+        LEFT JOIN budgets b ON b.budget_id = i.budget_id
+        LEFT JOIN last_record lr ON lr.budget_id = i.budget_id AND lr.n = 1
+        LEFT JOIN next_record nr ON nr.budget_id = i.budget_id AND nr.n = 1
+        LEFT JOIN last_initial li ON li.budget_id = i.budget_id AND li.n = 1
+        LEFT JOIN later_initial la ON la.budget_id = i.budget_id
+        ORDER BY i.budget_id;";
+    let mut histories = get_name_histories(db, instant)?;
+    let mut stmt = db.prepare(query)?;
+    let states = stmt
+        .query_map([instant], |row| {
+            let history = histories.remove(&row.get(0)?).unwrap_or_default();
+            map_budget_state(row, history.presence())
+        })?
+        .collect::<Result<Vec<Option<BudgetState>>, rusqlite::Error>>()?;
+    Ok(states.into_iter().flatten().collect())
+}
+
+// Replays name changes up to the instant, dropping the ones undone by then.
+fn get_name_histories(
+    db: &Connection,
+    instant: &DateTime<Utc>,
+) -> Result<HashMap<u32, NameHistory>, rusqlite::Error> {
+    let query = "
+        SELECT budget_id, action, field, old_value, new_value, created_at <= ?1
+        FROM audit
+        WHERE budget_id IS NOT NULL AND field IN ('name', 'operation')
+        ORDER BY budget_id, created_at, audit_id;";
+    let mut stmt = db.prepare(query)?;
+    let mut rows = stmt.query([instant])?;
+    let mut histories: HashMap<u32, NameHistory> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let history = histories.entry(row.get(0)?).or_default();
+        let change = NameChange {
+            action: row.get(1)?,
+            old_value: row.get(3)?,
+            new_value: row.get(4)?,
+        };
+        let field: String = row.get(2)?;
+        if field == "name" && history.first.is_none() {
+            history.first = Some(change.before());
+        }
+        if !row.get::<_, bool>(5)? {
+            continue;
+        }
+        let target = change.new_value.as_deref();
+        match (field.as_str(), change.action.as_str()) {
+            ("name", _) => {
+                history.undone.clear();
+                history.applied.push(change);
+            }
+            (_, "Undo") if history.applied.last().map(|last| last.action.as_str()) == target => {
+                history.undone.extend(history.applied.pop());
+            }
+            (_, "Redo") if history.undone.last().map(|last| last.action.as_str()) == target => {
+                history.applied.extend(history.undone.pop());
+            }
+            _ => (),
+        }
+    }
+    Ok(histories)
+}
+
+fn map_budget_state(
+    row: &rusqlite::Row,
+    presence: Presence,
+) -> Result<Option<BudgetState>, rusqlite::Error> {
+    let current_name: Option<String> = row.get(1)?;
+    let has_last_record: bool = row.get(5)?;
+    let name = match presence {
+        Presence::Existed(name) => name,
+        Presence::Absent => return Ok(None),
+        Presence::Unknown => match (&current_name, has_last_record) {
+            (Some(name), _) => Some(name.to_owned()),
+            (None, true) => None,
+            (None, false) => return Ok(None),
+        },
+    };
+    let current_funds = if has_last_record {
+        row.get(6)?
+    } else if row.get(7)? {
+        row.get(8)?
+    } else {
+        row.get(3)?
+    };
+    let initial_funds = if row.get(9)? {
+        row.get(10)?
+    } else if row.get(11)? {
+        None
+    } else {
+        row.get(2)?
+    };
+    Ok(Some(BudgetState {
+        budget_id: row.get(0)?,
+        name,
+        initial_funds,
+        current_funds,
+        currency: row.get(4)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{insert_audit_entry, AuditEntry};
+    use crate::budget::{insert_budget, Budget};
+    use crate::database::migrate;
+    use crate::record::{insert_record, Record};
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap()
+    }
+
+    fn audit(
+        db: &Connection,
+        id: u32,
+        action: &str,
+        old: Option<&str>,
+        new: Option<&str>,
+        day: u32,
+    ) {
+        let mut entry = AuditEntry::new(
+            &id,
+            action,
+            "name",
+            old.map(String::from),
+            new.map(String::from),
+            &None,
+        );
+        entry.created_at = at(day);
+        insert_audit_entry(db, &entry).unwrap();
+    }
+
+    fn operation(db: &Connection, id: u32, action: &str, target: &str, day: u32) {
+        let mut entry = AuditEntry::new(
+            &id,
+            action,
+            "operation",
+            None,
+            Some(target.to_string()),
+            &None,
+        );
+        entry.created_at = at(day);
+        insert_audit_entry(db, &entry).unwrap();
+    }
+
+    fn record(db: &Connection, id: u32, action: &str, amount: f64, before: f64, day: u32) {
+        let mut record = Record::new(&id, action, &amount, &None);
+        record.balance_before = Some(before);
+        record.balance_after = Some(before - amount);
+        record.created_at = at(day);
+        insert_record(db, &record).unwrap();
+    }
+
+    fn setup() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        migrate(&db).unwrap();
+        let mut food = Budget::new("Groceries", &300.0);
+        food.current_funds = 200.0;
+        insert_budget(&db, &food).unwrap();
+        insert_budget(&db, &Budget::new("Rent", &900.0)).unwrap();
+        audit(&db, 1, "Create budget", None, Some("Food"), 1);
+        record(&db, 1, "Reduce funds", 50.0, 300.0, 3);
+        audit(&db, 1, "Rename budget", Some("Food"), Some("Groceries"), 5);
+        record(&db, 1, "Reduce funds", 50.0, 250.0, 7);
+        audit(&db, 2, "Create budget", None, Some("Rent"), 6);
+        db
+    }
+
+    #[test]
+    fn get_budget_states_at_ok() {
+        let db = setup();
+        let states = get_budget_states_at(&db, &at(4)).unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].name, Some(String::from("Food")));
+        assert_eq!(states[0].current_funds, Some(250.0));
+        assert_eq!(states[0].initial_funds, Some(300.0));
+        let states = get_budget_states_at(&db, &at(2)).unwrap();
+        assert_eq!(states[0].current_funds, Some(300.0));
+        let states = get_budget_states_at(&db, &at(8)).unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].name, Some(String::from("Groceries")));
+        assert_eq!(states[0].current_funds, Some(200.0));
+        assert_eq!(states[1].current_funds, Some(900.0));
+    }
+
+    #[test]
+    fn get_budget_states_at_ko() {
+        let db = setup();
+        audit(&db, 3, "Create budget", None, Some("Trip"), 2);
+        audit(&db, 3, "Remove budget", Some("Trip"), None, 4);
+        let before = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        assert!(get_budget_states_at(&db, &before).unwrap().is_empty());
+        let states = get_budget_states_at(&db, &at(3)).unwrap();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[1].name, Some(String::from("Trip")));
+        let states = get_budget_states_at(&db, &at(4)).unwrap();
+        assert_eq!(states.len(), 1);
+    }
+
+    #[test]
+    fn get_budget_states_at_undo_ok() {
+        let db = setup();
+        operation(&db, 1, "Undo", "Rename budget", 8);
+        audit(&db, 3, "Create budget", None, Some("Trip"), 8);
+        operation(&db, 3, "Undo", "Create budget", 9);
+        let states = get_budget_states_at(&db, &at(8)).unwrap();
+        assert_eq!(states[0].name, Some(String::from("Food")));
+        assert_eq!(states.len(), 3);
+        let states = get_budget_states_at(&db, &at(9)).unwrap();
+        assert_eq!(states.len(), 2);
+        operation(&db, 1, "Redo", "Rename budget", 10);
+        let states = get_budget_states_at(&db, &at(10)).unwrap();
+        assert_eq!(states[0].name, Some(String::from("Groceries")));
+        let states = get_budget_states_at(&db, &at(7)).unwrap();
+        assert_eq!(states[0].name, Some(String::from("Groceries")));
+    }
+}
@@ -5,3 +5,49 @@ pub fn capitalize(text: &str) -> String {
         Some(letter) => letter.to_uppercase().collect::<String>() + chars.as_str(),
     }
 }
+
+pub fn matches_pattern(text: &str, pattern: &str) -> bool {
+    let (text, pattern) = (text.to_lowercase(), pattern.to_lowercase());
+    if !pattern.contains(['*', '?']) {
+        return text.contains(&pattern);
+    }
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            t += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_pattern_ok() {
+        assert!(matches_pattern("Groceries", "gro"));
+        assert!(matches_pattern("Groceries", "g*s"));
+        assert!(matches_pattern("Trip 2024", "trip ????"));
+    }
+
+    #[test]
+    fn matches_pattern_ko() {
+        assert!(!matches_pattern("Groceries", "rent"));
+        assert!(!matches_pattern("Groceries", "g*x"));
+        assert!(!matches_pattern("Trip 24", "trip ????"));
+    }
+}